@@ -0,0 +1,48 @@
+// Compare cost of sending messages through the mpsc channel and through per thread rings
+//
+// Run with `cargo bench`
+
+use pragmatic_logger::{BufferSize, Level, LoggerBuilder, Sink};
+
+const THREADS: usize = 4;
+const MESSAGES_PER_THREAD: usize = 200_000;
+
+/// Send `MESSAGES_PER_THREAD` trace messages from each of `THREADS` threads
+///
+/// Returns average time per send in nanoseconds
+fn run(builder: LoggerBuilder) -> f64 {
+    let log = builder
+        .add_sink(Sink::stderr()) // Only written on dump, which never happens here
+        .build()
+        .expect("Failed to build logger");
+
+    let start = std::time::Instant::now();
+    let handles: Vec<_> = (0..THREADS)
+        .map(|_| {
+            let log = log.clone();
+            std::thread::spawn(move || {
+                for _k in 0..MESSAGES_PER_THREAD {
+                    log.trace_str("benchmark message");
+                }
+            })
+        })
+        .collect();
+    for h in handles {
+        h.join().expect("Sending thread panicked");
+    }
+    let elapsed = start.elapsed();
+
+    log.shutdown();
+    elapsed.as_nanos() as f64 / (THREADS * MESSAGES_PER_THREAD) as f64
+}
+
+fn main() {
+    let new_builder = || LoggerBuilder::new(Level::Trace, Level::Error, BufferSize::Size1024);
+
+    let channel = run(new_builder());
+    let rings = run(new_builder().per_thread_rings(BufferSize::Size2048));
+
+    println!("{} threads x {} messages", THREADS, MESSAGES_PER_THREAD);
+    println!("mpsc channel:      {:>8.1} ns per send", channel);
+    println!("per thread rings:  {:>8.1} ns per send", rings);
+}
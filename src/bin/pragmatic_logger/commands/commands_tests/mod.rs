@@ -0,0 +1,179 @@
+#![cfg(test)]
+
+use super::*;
+
+fn data(level: Level, time: &str, line: &str) -> LogData {
+    let time_stamp = chrono::DateTime::parse_from_rfc3339(time).unwrap().with_timezone(&chrono::offset::Local);
+    LogData::new(level, time_stamp, line.to_string())
+}
+
+/// Write dump file of two incidents, returning its path
+fn dump_file(name: &str) -> String {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(&rw_dir).join(name);
+    let contents = [
+        "\n".to_string(),
+        data(Level::Info,  "2022-03-01T10:00:00+00:00", "test line 1").as_string(),
+        data(Level::Error, "2022-03-01T10:00:01+00:00", "disk full").as_string(),
+        "\n".to_string(),
+        "Dump requested: manual\n".to_string(),
+        data(Level::Trace, "2022-03-02T10:00:00+00:00", "test line 2").as_string(),
+        data(Level::Error, "2022-03-02T10:00:01+00:00", "disk full").as_json(),
+        data(Level::Error, "2022-03-02T10:00:02+00:00", "timeout").as_string(),
+    ].concat();
+    std::fs::write(&fp, contents).unwrap();
+    fp.to_str().unwrap().to_string()
+}
+
+fn run_str(args: &[&str]) -> Result<String, &'static str> {
+    let args: std::vec::Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    let mut out = std::vec::Vec::new();
+    run(&args, &mut out)?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+
+#[test]
+fn test_list_and_show() {
+    let fp = dump_file("test_list_and_show.txt");
+
+    let ans = run_str(&["list", &fp]).unwrap();
+    let lines: std::vec::Vec<&str> = ans.lines().collect();
+    assert!(2 == lines.len());
+    assert!(lines[0].contains("2 records") && lines[0].contains("1 errors"));
+    assert!(lines[1].contains("3 records") && lines[1].contains("2 errors") && lines[1].ends_with("manual"));
+
+    let ans = run_str(&["show", &fp, "2"]).unwrap();
+    assert!(4 == ans.lines().count());
+    assert!(ans.starts_with("Dump requested: manual\n"));
+    assert!(ans.contains("| test line 2\n"));
+
+    let ans = run_str(&["show", &fp, "1", "--json"]).unwrap();
+    assert!(2 == ans.lines().count());
+    assert!(ans.lines().all(|l| l.starts_with('{')));
+
+    assert!(Err("No such incident") == run_str(&["show", &fp, "3"]));
+    assert!(Err("No such incident") == run_str(&["show", &fp, "0"]));
+}
+
+#[test]
+fn test_filter() {
+    let fp = dump_file("test_filter.txt");
+
+    let ans = run_str(&["filter", &fp, "--level", "error"]).unwrap();
+    assert!(3 == ans.lines().count());
+
+    let ans = run_str(&["filter", &fp, "--level", "Error", "--regex", "^disk"]).unwrap();
+    assert!(2 == ans.lines().count());
+
+    let ans = run_str(&["filter", &fp, "--since", "2022-03-02T10:00:00+00:00", "--until", "2022-03-02T10:00:02+00:00"]).unwrap();
+    assert!(2 == ans.lines().count());
+    assert!(ans.contains("test line 2") && ans.contains("disk full"));
+
+    assert!(Err("Invalid level") == run_str(&["filter", &fp, "--level", "Fatal"]));
+    assert!(Err("Invalid regex") == run_str(&["filter", &fp, "--regex", "("]));
+    assert!(Err("Missing value for option") == run_str(&["filter", &fp, "--since"]));
+    assert!(Err("Unknown option") == run_str(&["filter", &fp, "--bogus"]));
+    assert!(Err("Unknown command") == run_str(&["bogus", &fp]));
+}
+
+#[test]
+fn test_convert() {
+    let fp = dump_file("test_convert.txt");
+    let fp_json = fp.replace(".txt", ".json");
+
+    assert!(run_str(&["convert", &fp, "--to", "json", "--output", &fp_json]).unwrap().is_empty());
+    let json = std::fs::read_to_string(&fp_json).unwrap();
+    assert!(5 == json.lines().filter(|l| l.starts_with('{')).count());
+
+    // Back to text gives the same records and headers
+    let text = run_str(&["convert", &fp_json, "--to", "text"]).unwrap();
+    let incidents = pragmatic_logger::parse_dump(&text);
+    let original = pragmatic_logger::read_dump_file(&fp).unwrap();
+    assert!(original.len() == incidents.len());
+    for (a, b) in original.iter().zip(incidents.iter()) {
+        assert!(a.reason == b.reason);
+        assert!(a.records.len() == b.records.len());
+        for (x, y) in a.records.iter().zip(b.records.iter()) {
+            assert!(x.as_string() == y.as_string());
+        }
+    }
+
+    assert!(Err("Missing --to") == run_str(&["convert", &fp]));
+}
+
+#[test]
+fn test_stats() {
+    let fp = dump_file("test_stats.txt");
+
+    let ans = run_str(&["stats", &fp, "--top", "1"]).unwrap();
+    assert!(ans.starts_with("Incidents: 2\n"));
+    assert!(2 == ans.lines().filter(|l| l.ends_with("       1") && l.trim_start().starts_with("2022-03-0")).count());
+    assert!(ans.contains("Error       3\n"));
+    assert!(ans.ends_with("Top error messages:\n       2  disk full\n"));
+}
+
+#[test]
+fn test_merge() {
+    let fp_1 = dump_file("test_merge_1.txt");
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp_2 = std::path::PathBuf::from(&rw_dir).join("test_merge_2.txt");
+    // Written with the offset in its time stamp, as by a host in that zone
+    let at = |time: &str, line: &str| {
+        let offset = *chrono::DateTime::parse_from_rfc3339(time).unwrap().offset();
+        data(Level::Error, time, line).as_string_at(&offset)
+    };
+    std::fs::write(&fp_2, [
+        "\n".to_string(),
+        at("2022-03-01T11:59:59+02:00", "test line 3"), // Before all of file 1
+        at("2022-03-02T05:00:03-05:00", "test line 4"), // Last
+    ].concat()).unwrap();
+    let fp_2 = fp_2.to_str().unwrap();
+
+    let ans = run_str(&["merge", &fp_1, fp_2]).unwrap();
+    let lines: std::vec::Vec<&str> = ans.lines().collect();
+    assert!(7 == lines.len());
+    assert!(lines[0].starts_with(&format!("{} | ", fp_2)) && lines[0].ends_with("test line 3"));
+    assert!(lines[1].starts_with(&format!("{} | ", fp_1)) && lines[1].ends_with("test line 1"));
+    assert!(lines[6].ends_with("test line 4"));
+
+    // Each record keeps the UTC offset of its file
+    assert!(lines[0].contains(" | 2022-03-01T11:59:59+02:00 "));
+    assert!(lines[6].contains(" | 2022-03-02T05:00:03-05:00 "));
+
+    let ans = run_str(&["merge", &fp_1, fp_2, "--regex", "line [34]", "--json"]).unwrap();
+    assert!(2 == ans.lines().count());
+    assert!(ans.contains(&format!("\"source\":\"{}\"", fp_2)));
+    assert!(ans.contains("\"timestamp\":\"2022-03-01T11:59:59+02:00\""));
+}
+
+#[test]
+fn test_written_offset_kept() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(&rw_dir).join("test_written_offset_kept.txt");
+    // Written by a host at +09:00, a day later than in UTC, and cut short by a crash
+    let time = "2022-03-02T01:00:00+09:00";
+    let offset = *chrono::DateTime::parse_from_rfc3339(time).unwrap().offset();
+    std::fs::write(&fp, [
+        "\n".to_string(),
+        data(Level::Error, time, "test line 1").as_string_at(&offset),
+        "2022-03-02T01:00:01+09:00 | Err".to_string(),
+    ].concat()).unwrap();
+    let fp = fp.to_str().unwrap();
+
+    assert!(run_str(&["list", fp]).unwrap().contains(time));
+    let ans = run_str(&["show", fp, "1"]).unwrap();
+    assert!(ans.starts_with(time) && ans.contains("| Error | test line 1\n"));
+    assert!(run_str(&["filter", fp, "--json"]).unwrap().contains(&format!("\"timestamp\":\"{}\"", time)));
+
+    let ans = run_str(&["stats", fp]).unwrap();
+    assert!(ans.contains("  2022-03-02       1\n"));
+
+    // Line that could not be parsed is kept as it was
+    let ans = run_str(&["convert", fp, "--to", "json"]).unwrap();
+    assert!(ans.contains(&format!("\"timestamp\":\"{}\"", time)));
+    assert!(ans.ends_with("\n2022-03-02T01:00:01+09:00 | Err\n"));
+}
@@ -0,0 +1,283 @@
+// commands module
+//
+// Each command reads a dump file with the library's reader and writes its report to `out`.
+// Time stamps are written with the UTC offset they had in the file, not the local one.
+
+use pragmatic_logger::{Incident, Level, LogData};
+
+#[cfg(test)]
+mod commands_tests;
+
+const DEFAULT_TOP: usize = 10;
+const WRITE_FAILED: &str = "Failed to write output";
+
+/// Format records are written in
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum Format {
+    Text,
+    Json,
+}
+
+/// Options following the positional arguments
+struct Options {
+    level: Option<Level>,
+    since: Option<chrono::DateTime<chrono::offset::Local>>,
+    until: Option<chrono::DateTime<chrono::offset::Local>>,
+    regex: Option<regex::Regex>,
+    format: Format,
+    to: Option<Format>,
+    output: Option<String>,
+    top: usize,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, &'static str> {
+        let mut ans = Self {
+            level: None,
+            since: None,
+            until: None,
+            regex: None,
+            format: Format::Text,
+            to: None,
+            output: None,
+            top: DEFAULT_TOP,
+        };
+        let mut it = args.iter();
+        while let Some(a) = it.next() {
+            match a.as_str() {
+                "--json" => ans.format = Format::Json,
+                "--level" => ans.level = Some(parse_level(value(&mut it)?)?),
+                "--since" => ans.since = Some(parse_time(value(&mut it)?)?),
+                "--until" => ans.until = Some(parse_time(value(&mut it)?)?),
+                "--regex" => ans.regex = Some(regex::Regex::new(value(&mut it)?).map_err(|_| "Invalid regex")?),
+                "--to" => ans.to = Some(parse_format(value(&mut it)?)?),
+                "--output" => ans.output = Some(value(&mut it)?.to_string()),
+                "--top" => ans.top = value(&mut it)?.parse().map_err(|_| "Invalid count for --top")?,
+                _ => return Err("Unknown option"),
+            }
+        }
+        Ok(ans)
+    }
+
+    /// Record passes every filter given
+    // `Option::is_none_or` would need Rust 1.82
+    #[allow(clippy::unnecessary_map_or)]
+    fn is_match(&self, d: &LogData) -> bool {
+        self.level.map_or(true, |l| d.level() <= l)
+            && self.since.map_or(true, |t| d.time_stamp() >= t)
+            && self.until.map_or(true, |t| d.time_stamp() < t)
+            && self.regex.as_ref().map_or(true, |r| r.is_match(d.line()))
+    }
+}
+
+
+/// Run command given by `args`, not including the program name
+pub fn run(args: &[String], out: &mut impl std::io::Write) -> Result<(), &'static str> {
+    let command = args.first().ok_or("Missing command")?;
+    let file = args.get(1).ok_or("Missing file")?;
+    match command.as_str() {
+        "list" => {
+            Options::parse(&args[2..])?;
+            list(&pragmatic_logger::read_dump_file(file)?, out)
+        }
+        "show" => {
+            let index: usize = args.get(2).ok_or("Missing incident")?.parse().map_err(|_| "Invalid incident")?;
+            let options = Options::parse(&args[3..])?;
+            let incidents = pragmatic_logger::read_dump_file(file)?;
+            let incident = index.checked_sub(1).and_then(|k| incidents.get(k)).ok_or("No such incident")?;
+            write_incident(incident, options.format, out)
+        }
+        "filter" => {
+            let options = Options::parse(&args[2..])?;
+            for incident in pragmatic_logger::read_dump_file(file)? {
+                for (d, offset) in incident.records.iter().zip(incident.offsets.iter()) {
+                    if options.is_match(d) {
+                        write_record(d, offset, options.format, out)?;
+                    }
+                }
+            }
+            Ok(())
+        }
+        "convert" => {
+            let options = Options::parse(&args[2..])?;
+            let format = options.to.ok_or("Missing --to")?;
+            let incidents = pragmatic_logger::read_dump_file(file)?;
+            match options.output {
+                Some(path) => {
+                    let mut f = std::fs::File::create(path).map_err(|_| "Failed to create output file")?;
+                    convert(&incidents, format, &mut f)
+                }
+                None => convert(&incidents, format, out),
+            }
+        }
+        "merge" => {
+            let files: std::vec::Vec<&str> = args[1..]
+                .iter()
+                .take_while(|a| !a.starts_with("--"))
+                .map(|a| a.as_str())
+                .collect();
+            let options = Options::parse(&args[1 + files.len()..])?;
+            for m in pragmatic_logger::merge_dump_files(&files)? {
+                if options.is_match(&m.record) {
+                    write_merged(m, options.format, out)?;
+                }
+            }
+            Ok(())
+        }
+        "stats" => {
+            let options = Options::parse(&args[2..])?;
+            stats(&pragmatic_logger::read_dump_file(file)?, options.top, out)
+        }
+        _ => Err("Unknown command"),
+    }
+}
+
+
+/// One line per incident: number, time of first record, record and error counts, reason
+fn list(incidents: &[Incident], out: &mut impl std::io::Write) -> Result<(), &'static str> {
+    for (k, incident) in incidents.iter().enumerate() {
+        let first = incident.records.first()
+            .zip(incident.offsets.first())
+            .map_or("-".to_string(), |(d, offset)| d.time_stamp().with_timezone(offset).to_rfc3339());
+        let errors = incident.records.iter().filter(|d| Level::Error == d.level()).count();
+        let mut line = format!("{:>4}  {:<35}  {:>6} records  {:>6} errors", k + 1, first, incident.records.len(), errors);
+        if incident.is_recovered {
+            line += "  Recovered from previous crash";
+        }
+        if let Some(r) = incident.reason.as_ref() {
+            line += &format!("  {}", r);
+        }
+        writeln!(out, "{}", line).map_err(|_| WRITE_FAILED)?;
+    }
+    Ok(())
+}
+
+/// Write the whole file in `format`, keeping the dump headers
+///
+/// Lines that could not be parsed are written unchanged after the records of their incident.
+fn convert(incidents: &[Incident], format: Format, out: &mut impl std::io::Write) -> Result<(), &'static str> {
+    for incident in incidents {
+        out.write_all(b"\n").map_err(|_| WRITE_FAILED)?;
+        write_incident(incident, format, out)?;
+        for line in incident.unparsed.iter() {
+            writeln!(out, "{}", line).map_err(|_| WRITE_FAILED)?;
+        }
+    }
+    Ok(())
+}
+
+/// Incidents per day of their first record, records per level, and the most common error messages
+///
+/// Days are those of the file, using the UTC offset the first record was written with.
+fn stats(incidents: &[Incident], top: usize, out: &mut impl std::io::Write) -> Result<(), &'static str> {
+    let mut per_day = std::collections::BTreeMap::<String, usize>::new();
+    let mut per_level = [0_usize; 5];
+    let mut errors = std::collections::HashMap::<&str, usize>::new();
+    for incident in incidents {
+        if let Some((d, offset)) = incident.records.first().zip(incident.offsets.first()) {
+            *per_day.entry(d.time_stamp().with_timezone(offset).format("%Y-%m-%d").to_string()).or_insert(0) += 1;
+        }
+        for d in incident.records.iter() {
+            per_level[d.level() as usize] += 1;
+            if Level::Error == d.level() {
+                *errors.entry(d.line()).or_insert(0) += 1;
+            }
+        }
+    }
+
+    let mut ans = format!("Incidents: {}\n", incidents.len());
+    ans += "\nIncidents per day:\n";
+    for (day, count) in per_day.iter() {
+        ans += &format!("  {}  {:>6}\n", day, count);
+    }
+    ans += "\nRecords per level:\n";
+    for level in [Level::Error, Level::Warn, Level::Info, Level::Trace] {
+        ans += &format!("  {}  {:>6}\n", level, per_level[level as usize]);
+    }
+    ans += "\nTop error messages:\n";
+    let mut errors: std::vec::Vec<(&str, usize)> = errors.into_iter().collect();
+    // Most common first, then alphabetical so output is stable
+    errors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    for (line, count) in errors.into_iter().take(top) {
+        ans += &format!("  {:>6}  {}\n", count, line);
+    }
+    out.write_all(ans.as_bytes()).map_err(|_| WRITE_FAILED)
+}
+
+
+/// Header lines then records, as written by a dump
+fn write_incident(incident: &Incident, format: Format, out: &mut impl std::io::Write) -> Result<(), &'static str> {
+    let mut header = String::new();
+    if incident.is_recovered {
+        header += "Recovered from previous crash\n";
+    }
+    if let Some(r) = incident.reason.as_ref() {
+        header += &format!("Dump requested: {}\n", r);
+    }
+    if let Some(s) = incident.stats.as_ref() {
+        header += &format!("Stats: {}\n", s);
+    }
+    out.write_all(header.as_bytes()).map_err(|_| WRITE_FAILED)?;
+    for (d, offset) in incident.records.iter().zip(incident.offsets.iter()) {
+        write_record(d, offset, format, out)?;
+    }
+    Ok(())
+}
+
+/// Record tagged with the file it came from,
+/// "<file> | <record>" in text, or a `"source"` custom field in JSON
+///
+/// Time stamps keep the UTC offset they were written with in their file.
+fn write_merged(m: pragmatic_logger::MergedRecord, format: Format, out: &mut impl std::io::Write) -> Result<(), &'static str> {
+    let line = match format {
+        Format::Text => format!("{} | {}", m.source, m.record.as_string_at(&m.offset)),
+        Format::Json => {
+            let mut fields = m.record.fields().to_vec();
+            fields.push(("source".to_string(), m.source));
+            m.record.with_fields(fields).as_json_at(&m.offset)
+        }
+    };
+    out.write_all(line.as_bytes()).map_err(|_| WRITE_FAILED)
+}
+
+/// Record with its time stamp at `offset`, the UTC offset it was written with
+fn write_record(d: &LogData, offset: &chrono::FixedOffset, format: Format, out: &mut impl std::io::Write) -> Result<(), &'static str> {
+    let line = match format {
+        Format::Text => d.as_string_at(offset),
+        Format::Json => d.as_json_at(offset),
+    };
+    out.write_all(line.as_bytes()).map_err(|_| WRITE_FAILED)
+}
+
+fn value<'a>(it: &mut impl Iterator<Item = &'a String>) -> Result<&'a str, &'static str> {
+    it.next().map(|s| s.as_str()).ok_or("Missing value for option")
+}
+
+fn parse_level(s: &str) -> Result<Level, &'static str> {
+    match s.to_ascii_lowercase().as_str() {
+        "trace" => Ok(Level::Trace),
+        "info" => Ok(Level::Info),
+        "warn" => Ok(Level::Warn),
+        "error" => Ok(Level::Error),
+        _ => Err("Invalid level"),
+    }
+}
+
+fn parse_format(s: &str) -> Result<Format, &'static str> {
+    match s {
+        "text" => Ok(Format::Text),
+        "json" => Ok(Format::Json),
+        _ => Err("Invalid format, expected text or json"),
+    }
+}
+
+/// RFC 3339, or a date meaning local midnight at its start
+fn parse_time(s: &str) -> Result<chrono::DateTime<chrono::offset::Local>, &'static str> {
+    if let Ok(t) = chrono::DateTime::parse_from_rfc3339(s) {
+        return Ok(t.with_timezone(&chrono::offset::Local));
+    }
+    let date = chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|_| "Invalid time, expected RFC 3339 or YYYY-MM-DD")?;
+    chrono::TimeZone::from_local_datetime(&chrono::offset::Local, &date.and_hms(0, 0, 0))
+        .earliest()
+        .ok_or("Invalid time, expected RFC 3339 or YYYY-MM-DD")
+}
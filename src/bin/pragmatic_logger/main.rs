@@ -0,0 +1,42 @@
+// pragmatic-logger
+//
+// Command line tool for inspecting dump files written by pragmatic_logger.
+
+mod commands;
+
+const USAGE: &str = "\
+Usage: pragmatic-logger <command> <file> [options]
+
+Commands:
+  list <file>                 List incidents, one per line
+  show <file> <incident>      Show one incident, numbered from 1 as in list
+  filter <file>               Show records from every incident that match all options given
+      --level <level>         This severe or more, one of Trace, Info, Warn, Error
+      --since <time>          At or after time, RFC 3339 or YYYY-MM-DD
+      --until <time>          Before time, RFC 3339 or YYYY-MM-DD
+      --regex <pattern>       Payload line matches pattern
+  convert <file>              Write the file in another format, incidents kept
+      --to <text|json>        Format to write, required
+      --output <path>         Write to path instead of standard output
+  merge <file> <file>...      Show records from every file in one timeline, tagged with their file,
+                              filter options apply
+  stats <file>                Summary of incidents per day, records per level and top error messages
+      --top <count>           Number of error messages, default 10
+
+show and filter write text, or JSON with --json.
+";
+
+fn main() {
+    let args: std::vec::Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|a| "--help" == a || "-h" == a) {
+        print!("{}", USAGE);
+        return;
+    }
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    if let Err(e) = commands::run(&args, &mut out) {
+        eprintln!("Error: {}\n\n{}", e, USAGE);
+        std::process::exit(2);
+    }
+}
@@ -31,11 +31,34 @@
 //!
 
 mod log_common;
+mod log_channel;
 mod log_receiver;
+mod logger_builder;
+mod log_stats;
+#[cfg(unix)]
+mod log_daemon;
+mod log_reader;
 
 pub mod log_sender;
-pub use log_common::Level;
-pub use log_receiver::BufferSize;
+pub use log_sender::SendFailurePolicy;
+pub use log_common::{Level, LogData, Origin, SourceLocation};
+pub use log_common::wire;
+pub use log_receiver::{BufferSize, TriggerPolicy};
+pub use log_channel::OverflowPolicy;
+pub use log_stats::Stats;
+pub use log_receiver::circular_buffer::TextDataWriter;
+pub use log_receiver::circular_buffer::data_writer::{DataWriter, StderrWriter, NetworkWriter, NetworkProtocol};
+pub use log_receiver::sink::{Sink, SinkSnapshot, CRASH_SLOT_SIZE, Formatter, text_formatter, json_formatter};
+#[cfg(unix)]
+pub use log_receiver::sink::{syslog, journald};
+#[cfg(unix)]
+pub use log_receiver::circular_buffer::data_writer::{DatagramWriter, JournaldWriter};
+pub use logger_builder::LoggerBuilder;
+#[cfg(feature = "config")]
+pub use logger_builder::{ConfigError, ENV_PREFIX};
+pub use log_reader::{Incident, MergedRecord, read_dump_file, merge_dump_files, parse_dump, parse_line};
+#[cfg(unix)]
+pub use log_daemon::LogDaemon;
 
 
 /// Construct a new log sender and receiver pair
@@ -112,36 +135,15 @@ pub use log_receiver::BufferSize;
 ///     Ok(())
 /// }
 /// ```
-
 pub fn build_logger(
     log_file_path: &str,
     store_log_level: Level,
     dump_log_level: Level,
     buffer_size: BufferSize,
 ) -> Result<log_sender::LogSender, &'static str> {
-    let fp = std::path::PathBuf::from(log_file_path);
-
-    if fp.is_dir() {
-        Err("File path should be a plain file, not a directory")
-    } else if fp.parent().is_none() {
-        Err("Log file location does not seem to be valid. Are you trying to write to root?")
-    } else if store_log_level < dump_log_level {
-        Err("Must satisfy store_log_level >= dump_log_level")
-    } else if !buffer_size.is_valid(){
-        Err("Specified buffer_size is not a supported value. Must be of type BufferSize")
-    }
-    else {
-        let (sender, receiver) = std::sync::mpsc::channel::<log_common::LogData>();
-
-        if log_common::Level::Off != dump_log_level && log_common::Level::Off != store_log_level {
-            // Only bother to spawn receiver if data is going to be logged
-            let _log_rx_handle = log_receiver::spawn(dump_log_level, receiver, fp, buffer_size);
-            if _log_rx_handle.is_none(){
-                return Err("Failed to spawn receiver thread");
-            }
-        }
+    let sink = Sink::file(log_file_path)?;
 
-        let log_tx = log_sender::LogSender::new(sender, store_log_level);
-        Ok(log_tx)
-    }
+    LoggerBuilder::new(store_log_level, dump_log_level, buffer_size)
+        .add_sink(sink)
+        .build()
 }
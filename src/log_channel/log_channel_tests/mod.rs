@@ -0,0 +1,239 @@
+#![cfg(test)]
+
+use super::*;
+
+const TIMEOUT: std::time::Duration = std::time::Duration::from_millis(100);
+
+fn data(level: log_common::Level, line: &str) -> Message {
+    log_common::LogData::new(level, log_common::get_time_now(), line.to_string()).into()
+}
+
+/// Unwrap record from received message
+fn record(m: Result<Message, RecvTimeoutError>) -> log_common::LogData {
+    match m {
+        Ok(Message::Record(d)) => d,
+        _ => panic!("expected a record"),
+    }
+}
+
+
+#[test]
+fn test_unbounded() {
+    let (sender, mut receiver) = unbounded();
+    for k in 0..100 {
+        assert!(sender.send(data(log_common::Level::Info, &format!("{}", k))).is_ok());
+    }
+    for k in 0..100 {
+        assert!(record(receiver.recv_timeout(TIMEOUT)).line() == format!("{}", k));
+    }
+    assert!(Err(RecvTimeoutError::Timeout) == receiver.recv_timeout(TIMEOUT).map(|_| ()));
+    assert!(0 == receiver.take_dropped());
+
+    drop(sender);
+    assert!(Err(RecvTimeoutError::Disconnected) == receiver.recv_timeout(TIMEOUT).map(|_| ()));
+}
+
+
+#[test]
+fn test_bounded_drop_newest() {
+    let (sender, mut receiver) = bounded(4, OverflowPolicy::DropNewest);
+    for k in 0..4 {
+        assert!(Ok(Sent::Queued) == sender.send(data(log_common::Level::Info, &format!("{}", k))).map_err(|_| ()));
+    }
+    for k in 4..10 {
+        assert!(Ok(Sent::Dropped) == sender.send(data(log_common::Level::Info, &format!("{}", k))).map_err(|_| ()));
+    }
+
+    for k in 0..4 {
+        assert!(record(receiver.recv_timeout(TIMEOUT)).line() == format!("{}", k));
+    }
+    assert!(receiver.recv_timeout(TIMEOUT).is_err());
+    assert!(6 == receiver.take_dropped());
+    assert!(0 == receiver.take_dropped());
+}
+
+
+#[test]
+fn test_bounded_drop_oldest_below() {
+    let (sender, mut receiver) = bounded(4, OverflowPolicy::DropOldestBelow(log_common::Level::Warn));
+    assert!(sender.send(data(log_common::Level::Error, "error 1")).is_ok());
+    assert!(sender.send(data(log_common::Level::Info, "info 1")).is_ok());
+    assert!(sender.send(data(log_common::Level::Trace, "trace 1")).is_ok());
+    assert!(sender.send(data(log_common::Level::Warn, "warn 1")).is_ok());
+
+    // Full, oldest below Warn is "info 1"
+    assert!(sender.send(data(log_common::Level::Error, "error 2")).is_ok());
+    // Full, oldest below Warn is "trace 1"
+    assert!(sender.send(data(log_common::Level::Info, "info 2")).is_ok());
+    // Full, "info 2" is the only one below Warn
+    assert!(sender.send(data(log_common::Level::Warn, "warn 2")).is_ok());
+    // Full, nothing below Warn so newest is dropped
+    assert!(sender.send(data(log_common::Level::Info, "info 3")).is_ok());
+
+    let expected = ["error 1", "warn 1", "error 2", "warn 2"];
+    for e in expected.iter() {
+        assert!(record(receiver.recv_timeout(TIMEOUT)).line() == *e);
+    }
+    assert!(4 == receiver.take_dropped());
+}
+
+
+#[test]
+fn test_bounded_block() {
+    let (sender, mut receiver) = bounded(2, OverflowPolicy::Block);
+
+    let handle = std::thread::spawn(move || {
+        for k in 0..10 {
+            assert!(sender.send(data(log_common::Level::Info, &format!("{}", k))).is_ok());
+        }
+    });
+
+    for k in 0..10 {
+        assert!(record(receiver.recv_timeout(std::time::Duration::from_secs(5))).line() == format!("{}", k));
+    }
+    assert!(handle.join().is_ok());
+    assert!(0 == receiver.take_dropped());
+    assert!(Err(RecvTimeoutError::Disconnected) == receiver.recv_timeout(TIMEOUT).map(|_| ()));
+}
+
+
+#[test]
+fn test_bounded_commands_never_dropped() {
+    let (sender, mut receiver) = bounded(1, OverflowPolicy::DropNewest);
+    assert!(sender.send(data(log_common::Level::Info, "info 1")).is_ok());
+    assert!(sender.send(Message::Reconfigure(Reconfigure::PauseDumps)).is_ok());
+    assert!(sender.send(Message::Shutdown).is_ok());
+
+    assert!(record(receiver.recv_timeout(TIMEOUT)).level() == log_common::Level::Info);
+    assert!(matches!(receiver.recv_timeout(TIMEOUT), Ok(Message::Reconfigure(Reconfigure::PauseDumps))));
+    assert!(matches!(receiver.recv_timeout(TIMEOUT), Ok(Message::Shutdown)));
+    assert!(0 == receiver.take_dropped());
+}
+
+
+#[test]
+fn test_bounded_disconnect() {
+    let (sender, mut receiver) = bounded(2, OverflowPolicy::Block);
+    let sender_2 = sender.clone();
+    drop(sender);
+    assert!(sender_2.send(data(log_common::Level::Info, "info 1")).is_ok());
+    drop(sender_2);
+
+    // Queued data is still received before disconnect is reported
+    assert!(receiver.recv_timeout(TIMEOUT).is_ok());
+    assert!(Err(RecvTimeoutError::Disconnected) == receiver.recv_timeout(TIMEOUT).map(|_| ()));
+
+    let (sender, receiver) = bounded(2, OverflowPolicy::Block);
+    drop(receiver);
+    assert!(matches!(sender.send(data(log_common::Level::Info, "info 1")), Err(SendError(Message::Record(_)))));
+}
+
+
+
+#[test]
+fn test_per_thread_merge() {
+    const THREADS: usize = 4;
+    const MESSAGES: usize = 100;
+    let (sender, mut receiver) = per_thread(256);
+
+    let handles: std::vec::Vec<_> = (0..THREADS).map(|t| {
+        let sender = sender.clone();
+        std::thread::spawn(move || {
+            for k in 0..MESSAGES {
+                assert!(sender.send(data(log_common::Level::Info, &format!("{} {}", t, k))).is_ok());
+            }
+        })
+    }).collect();
+    for h in handles {
+        assert!(h.join().is_ok());
+    }
+
+    // All threads finished, so everything is merged into a single batch ordered by time
+    let mut last = log_common::get_time_now() - chrono::Duration::days(1);
+    let mut next_k = [0_usize; THREADS];
+    for _ in 0..THREADS * MESSAGES {
+        let d = record(receiver.recv_timeout(TIMEOUT));
+        assert!(d.time_stamp() >= last);
+        last = d.time_stamp();
+
+        // Order from each thread is kept
+        let (t, k) = d.line().split_once(' ').unwrap();
+        let t: usize = t.parse().unwrap();
+        assert!(next_k[t] == k.parse::<usize>().unwrap());
+        next_k[t] += 1;
+    }
+    assert!(0 == receiver.take_dropped());
+    assert!(Err(RecvTimeoutError::Timeout) == receiver.recv_timeout(TIMEOUT).map(|_| ()));
+
+    drop(sender);
+    assert!(Err(RecvTimeoutError::Disconnected) == receiver.recv_timeout(TIMEOUT).map(|_| ()));
+}
+
+
+#[test]
+fn test_per_thread_full() {
+    let (sender, mut receiver) = per_thread(16);
+    for k in 0..16 {
+        assert!(Ok(Sent::Queued) == sender.send(data(log_common::Level::Info, &format!("{}", k))).map_err(|_| ()));
+    }
+    for k in 16..20 {
+        assert!(Ok(Sent::Dropped) == sender.send(data(log_common::Level::Info, &format!("{}", k))).map_err(|_| ()));
+    }
+
+    for k in 0..16 {
+        assert!(record(receiver.recv_timeout(TIMEOUT)).line() == format!("{}", k));
+    }
+    assert!(4 == receiver.take_dropped());
+
+    // Room again after draining
+    assert!(Ok(Sent::Queued) == sender.send(data(log_common::Level::Info, "20")).map_err(|_| ()));
+    assert!(record(receiver.recv_timeout(TIMEOUT)).line() == "20");
+}
+
+
+#[test]
+fn test_per_thread_full_command_waits() {
+    let (sender, mut receiver) = per_thread(16);
+
+    let handle = std::thread::spawn(move || {
+        for k in 0..16 {
+            assert!(sender.send(data(log_common::Level::Info, &format!("{}", k))).is_ok());
+        }
+        // Ring is full, command waits for the receiver instead of being dropped
+        assert!(Ok(Sent::Queued) == sender.send(Message::Shutdown).map_err(|_| ()));
+        sender
+    });
+
+    for k in 0..16 {
+        assert!(record(receiver.recv_timeout(std::time::Duration::from_secs(5))).line() == format!("{}", k));
+    }
+    assert!(matches!(receiver.recv_timeout(std::time::Duration::from_secs(5)), Ok(Message::Shutdown)));
+    assert!(0 == receiver.take_dropped());
+
+    // Full ring with the receiver gone fails rather than waiting forever
+    let sender = handle.join().unwrap();
+    for k in 0..16 {
+        assert!(sender.send(data(log_common::Level::Info, &format!("{}", k))).is_ok());
+    }
+    drop(receiver);
+    assert!(matches!(sender.send(Message::Shutdown), Err(SendError(Message::Shutdown))));
+}
+
+
+#[test]
+fn test_per_thread_wakes_receiver() {
+    let (sender, mut receiver) = per_thread(16);
+
+    let handle = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(sender.send(data(log_common::Level::Error, "error 1")).is_ok());
+        sender
+    });
+
+    let d = record(receiver.recv_timeout(std::time::Duration::from_secs(5)));
+    assert!("error 1" == d.line());
+
+    let sender = handle.join().unwrap();
+    drop(receiver);
+    assert!(matches!(sender.send(data(log_common::Level::Info, "info 1")), Err(SendError(Message::Record(_)))));
+}
@@ -0,0 +1,362 @@
+// log channel module
+//
+// Connection from log senders to the log receiver.
+// Either an unbounded `std::sync::mpsc` channel,
+// a bounded queue with a policy for when it is full,
+// or lock free rings, one per sending thread.
+
+use super::log_common;
+use super::log_receiver;
+use super::log_stats;
+
+mod per_thread;
+#[cfg(unix)]
+mod socket;
+
+#[cfg(test)]
+mod log_channel_tests;
+
+/// Message carried from senders to the receiver
+#[derive(Debug)]
+pub enum Message {
+    /// Log message to be buffered
+    Record(log_common::LogData),
+    /// Receiver exits without dumping
+    Shutdown,
+    /// Acknowledged once every message sent before it has been handled
+    Flush(std::sync::mpsc::Sender<()>),
+    /// Dump the buffer now, whatever the dump level, with a reason for the dump
+    Dump(String),
+    /// Change how the receiver behaves
+    Reconfigure(Reconfigure),
+    /// Ask the receiver for information, answered on the channel in the query
+    Query(Query),
+}
+
+impl Message {
+    /// Commands must reach the receiver, so are never dropped or blocked by a bounded channel
+    fn is_command(&self) -> bool {
+        !matches!(self, Self::Record(_))
+    }
+
+    /// Level of a record, commands are treated as `Off` which is never less severe than anything
+    fn level(&self) -> log_common::Level {
+        match self {
+            Self::Record(d) => d.level(),
+            _ => log_common::Level::Off,
+        }
+    }
+}
+
+impl From<log_common::LogData> for Message {
+    fn from(d: log_common::LogData) -> Self {
+        Self::Record(d)
+    }
+}
+
+/// Information a sender can request from the receiver
+#[derive(Debug)]
+pub enum Query {
+    /// Current dump level
+    DumpLevel(std::sync::mpsc::Sender<log_common::Level>),
+    /// Counters about the logger
+    Stats(std::sync::mpsc::Sender<log_stats::Stats>),
+    /// Copy of the lines buffered by each sink, buffers are left untouched
+    Snapshot(std::sync::mpsc::Sender<std::vec::Vec<log_receiver::sink::SinkSnapshot>>),
+}
+
+/// Runtime changes to the receiver
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Reconfigure {
+    /// Messages this severe and more severe cause a dump, `Off` disables dumps
+    SetDumpLevel(log_common::Level),
+    /// Keep buffering but do not dump until resumed
+    PauseDumps,
+    ResumeDumps,
+    SetTriggerPolicy(log_receiver::TriggerPolicy),
+}
+
+
+/// What a bounded channel does when a message is sent while it is full
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum OverflowPolicy {
+    /// Sender waits until the receiver makes room
+    Block,
+    /// The message being sent is dropped
+    DropNewest,
+    /// The oldest queued message less severe than the given level is dropped to make room.
+    /// If there is none, the message being sent is dropped.
+    DropOldestBelow(log_common::Level),
+}
+
+
+/// Outcome of a message accepted by [`ChannelSender::send`]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Sent {
+    /// Message is on its way to the receiver
+    Queued,
+    /// Record dropped because the channel is full, counted by the receiver's `take_dropped`
+    Dropped,
+}
+
+
+/// Error returned when the receiving end is gone, with the message that could not be sent
+#[derive(Debug)]
+pub struct SendError(pub Message);
+
+/// Error returned from [`ChannelReceiver::recv_timeout`]
+#[derive(Debug, PartialEq)]
+pub enum RecvTimeoutError {
+    Timeout,
+    Disconnected,
+}
+
+
+/// Create an unbounded channel
+pub fn unbounded() -> (ChannelSender, ChannelReceiver) {
+    let (sender, receiver) = std::sync::mpsc::channel::<Message>();
+    (ChannelSender::Unbounded(sender), ChannelReceiver::Unbounded(receiver))
+}
+
+/// Create a channel holding at most `capacity` messages
+pub fn bounded(capacity: usize, policy: OverflowPolicy) -> (ChannelSender, ChannelReceiver) {
+    debug_assert!(0 < capacity);
+    let queue = std::sync::Arc::new(BoundedQueue {
+        capacity,
+        policy,
+        state: std::sync::Mutex::new(QueueState {
+            queue: std::collections::VecDeque::with_capacity(capacity),
+            senders: 1,
+            is_receiver_alive: true,
+            dropped: 0,
+        }),
+        not_empty: std::sync::Condvar::new(),
+        not_full: std::sync::Condvar::new(),
+    });
+    (ChannelSender::Bounded(queue.clone()), ChannelReceiver::Bounded(queue))
+}
+
+/// Create a channel where each sending thread has its own lock free ring of `ring_capacity` messages
+/// 
+/// `ring_capacity` must be a power of 2.
+/// Records sent while a thread's ring is full are dropped,
+/// commands wait until the receiver makes room.
+pub fn per_thread(ring_capacity: usize) -> (ChannelSender, ChannelReceiver) {
+    let registry = std::sync::Arc::new(per_thread::RingRegistry::new(ring_capacity));
+    (
+        ChannelSender::PerThread(registry.clone()),
+        ChannelReceiver::PerThread(registry, std::collections::VecDeque::new()),
+    )
+}
+
+/// Create the sending end of a channel to a `LogDaemon` listening on `socket_path`
+#[cfg(unix)]
+pub fn unix_socket(socket_path: &std::path::Path, origin: log_common::Origin) -> Result<ChannelSender, &'static str> {
+    let sender = socket::SocketSender::connect(socket_path, origin)?;
+    Ok(ChannelSender::Socket(std::sync::Arc::new(sender)))
+}
+
+/// Create the sending end of a channel to a `LogDaemon` over an already connected stream
+#[cfg(unix)]
+pub fn unix_stream(stream: std::os::unix::net::UnixStream, origin: log_common::Origin) -> Result<ChannelSender, &'static str> {
+    let sender = socket::SocketSender::from_stream(stream, origin)?;
+    Ok(ChannelSender::Socket(std::sync::Arc::new(sender)))
+}
+
+
+
+struct QueueState {
+    queue: std::collections::VecDeque<Message>,
+    senders: usize,
+    is_receiver_alive: bool,
+    dropped: u64,
+}
+
+pub struct BoundedQueue {
+    capacity: usize,
+    policy: OverflowPolicy,
+    state: std::sync::Mutex<QueueState>,
+    not_empty: std::sync::Condvar,
+    not_full: std::sync::Condvar,
+}
+
+impl BoundedQueue {
+
+    /// Lock state, a panic while holding the lock does not leave it inconsistent
+    fn lock(&self) -> std::sync::MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn send(&self, data: Message) -> Result<Sent, SendError> {
+        let mut state = self.lock();
+        if !state.is_receiver_alive {
+            return Err(SendError(data));
+        }
+
+        if state.queue.len() >= self.capacity && !data.is_command() {
+            match self.policy {
+                OverflowPolicy::Block => {
+                    while state.queue.len() >= self.capacity {
+                        state = self.not_full.wait(state).unwrap_or_else(|e| e.into_inner());
+                        if !state.is_receiver_alive {
+                            return Err(SendError(data));
+                        }
+                    }
+                }
+                OverflowPolicy::DropNewest => {
+                    state.dropped += 1;
+                    return Ok(Sent::Dropped);
+                }
+                OverflowPolicy::DropOldestBelow(level) => {
+                    if let Some(k) = state.queue.iter().position(|d| d.level() > level) {
+                        state.queue.remove(k);
+                        state.dropped += 1;
+                    } else {
+                        state.dropped += 1;
+                        return Ok(Sent::Dropped);
+                    }
+                }
+            }
+        }
+
+        state.queue.push_back(data);
+        self.not_empty.notify_one();
+        Ok(Sent::Queued)
+    }
+
+    fn recv_timeout(&self, timeout: std::time::Duration) -> Result<Message, RecvTimeoutError> {
+        let deadline = std::time::Instant::now() + timeout;
+        let mut state = self.lock();
+        loop {
+            if let Some(d) = state.queue.pop_front() {
+                self.not_full.notify_one();
+                return Ok(d);
+            } else if 0 == state.senders {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+
+            let now = std::time::Instant::now();
+            if now >= deadline {
+                return Err(RecvTimeoutError::Timeout);
+            }
+            state = self.not_empty
+                .wait_timeout(state, deadline - now)
+                .map(|(s, _)| s)
+                .unwrap_or_else(|e| e.into_inner().0);
+        }
+    }
+}
+
+
+/// Sending end of a channel, clone to get more senders
+pub enum ChannelSender {
+    Unbounded(std::sync::mpsc::Sender<Message>),
+    Bounded(std::sync::Arc<BoundedQueue>),
+    PerThread(std::sync::Arc<per_thread::RingRegistry>),
+    /// Receiver in another process
+    #[cfg(unix)]
+    Socket(std::sync::Arc<socket::SocketSender>),
+}
+
+impl ChannelSender {
+
+    /// Send message
+    ///
+    /// Fails only if the receiver is gone.
+    /// A record dropped due to the overflow policy is not an error, it is reported as `Sent::Dropped`.
+    /// Commands are never dropped.
+    pub fn send(&self, data: Message) -> Result<Sent, SendError> {
+        match self {
+            Self::Unbounded(s) => s.send(data).map(|_| Sent::Queued).map_err(|e| SendError(e.0)),
+            Self::Bounded(q) => q.send(data),
+            Self::PerThread(r) => r.send(data),
+            #[cfg(unix)]
+            Self::Socket(s) => s.send(data),
+        }
+    }
+}
+
+impl Clone for ChannelSender {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Unbounded(s) => Self::Unbounded(s.clone()),
+            Self::Bounded(q) => {
+                q.lock().senders += 1;
+                Self::Bounded(q.clone())
+            }
+            Self::PerThread(r) => {
+                r.add_sender();
+                Self::PerThread(r.clone())
+            }
+            #[cfg(unix)]
+            Self::Socket(s) => Self::Socket(s.clone()),
+        }
+    }
+}
+
+impl Drop for ChannelSender {
+    fn drop(&mut self) {
+        match self {
+            Self::Unbounded(_) => (),
+            Self::Bounded(q) => {
+                q.lock().senders -= 1;
+                q.not_empty.notify_all();
+            }
+            Self::PerThread(r) => r.remove_sender(),
+            #[cfg(unix)]
+            Self::Socket(_) => (), // Socket closes when the last clone is dropped
+        }
+    }
+}
+
+
+/// Receiving end of a channel
+pub enum ChannelReceiver {
+    Unbounded(std::sync::mpsc::Receiver<Message>),
+    Bounded(std::sync::Arc<BoundedQueue>),
+    /// Registry of rings, and messages drained from the rings not yet received
+    PerThread(std::sync::Arc<per_thread::RingRegistry>, std::collections::VecDeque<Message>),
+}
+
+impl ChannelReceiver {
+
+    /// Wait up to `timeout` for a message
+    pub fn recv_timeout(&mut self, timeout: std::time::Duration) -> Result<Message, RecvTimeoutError> {
+        match self {
+            Self::Unbounded(r) => r.recv_timeout(timeout).map_err(|e| match e {
+                std::sync::mpsc::RecvTimeoutError::Timeout => RecvTimeoutError::Timeout,
+                std::sync::mpsc::RecvTimeoutError::Disconnected => RecvTimeoutError::Disconnected,
+            }),
+            Self::Bounded(q) => q.recv_timeout(timeout),
+            Self::PerThread(r, pending) => r.recv_timeout(pending, timeout),
+        }
+    }
+
+    /// Read and reset the number of messages dropped since the last call
+    pub fn take_dropped(&self) -> u64 {
+        match self {
+            Self::Unbounded(_) => 0,
+            Self::Bounded(q) => std::mem::take(&mut q.lock().dropped),
+            Self::PerThread(r, _) => r.take_dropped(),
+        }
+    }
+}
+
+impl From<std::sync::mpsc::Receiver<Message>> for ChannelReceiver {
+    fn from(r: std::sync::mpsc::Receiver<Message>) -> Self {
+        Self::Unbounded(r)
+    }
+}
+
+impl Drop for ChannelReceiver {
+    fn drop(&mut self) {
+        match self {
+            Self::Unbounded(_) => (),
+            Self::Bounded(q) => {
+                q.lock().is_receiver_alive = false;
+                q.not_full.notify_all();
+            }
+            Self::PerThread(r, _) => r.close_receiver(),
+        }
+    }
+}
@@ -0,0 +1,292 @@
+// per thread module
+//
+// Each sending thread writes into its own lock free single producer single consumer ring.
+// The receiver drains all rings and merges the messages by time stamp.
+
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use super::log_common;
+use super::{Message, RecvTimeoutError, SendError, Sent};
+
+/// Longest the receiver sleeps before checking the rings again
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(5);
+/// Wait before a command tries again to get into a full ring
+const RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_millis(1);
+
+/// Source of unique ids so a thread can hold rings for several registries
+static NEXT_REGISTRY_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// Rings owned by this thread, one per registry it has sent to
+    static THREAD_RINGS: std::cell::RefCell<std::vec::Vec<(usize, Arc<SpscRing>)>> =
+        const { std::cell::RefCell::new(std::vec::Vec::new()) };
+}
+
+
+/// Fixed size lock free ring with exactly one producer thread and one consumer thread
+struct SpscRing {
+    slots: Box<[UnsafeCell<MaybeUninit<Message>>]>,
+    mask: usize,
+    /// Next index to read, only written by consumer
+    head: AtomicUsize,
+    /// Next index to write, only written by producer
+    tail: AtomicUsize,
+    dropped: AtomicU64,
+    /// Set when the registry is gone so the producer thread can forget the ring
+    is_orphaned: AtomicBool,
+}
+
+// Safety: a slot is only accessed by the producer before `tail` is published,
+// and only by the consumer after it observed `tail` and before it publishes `head`.
+unsafe impl Sync for SpscRing {}
+unsafe impl Send for SpscRing {}
+
+impl SpscRing {
+    fn new(capacity: usize) -> Self {
+        debug_assert!(1 == capacity.count_ones());
+        let slots = (0..capacity)
+            .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+            .collect::<std::vec::Vec<_>>()
+            .into_boxed_slice();
+        Self {
+            slots,
+            mask: capacity - 1,
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            dropped: AtomicU64::new(0),
+            is_orphaned: AtomicBool::new(false),
+        }
+    }
+
+    /// Producer side, gives the message back if the ring is full
+    fn push(&self, data: Message) -> Result<(), Message> {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let head = self.head.load(Ordering::Acquire);
+        if tail.wrapping_sub(head) > self.mask {
+            Err(data)
+        } else {
+            // Safety: slot is not visible to the consumer until tail is stored
+            unsafe { (*self.slots[tail & self.mask].get()).write(data); }
+            self.tail.store(tail.wrapping_add(1), Ordering::Release);
+            Ok(())
+        }
+    }
+
+    /// Consumer side
+    fn pop(&self) -> Option<Message> {
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::Acquire);
+        if head == tail {
+            None
+        } else {
+            // Safety: slot was initialised by the producer before tail was stored
+            let data = unsafe { (*self.slots[head & self.mask].get()).assume_init_read() };
+            self.head.store(head.wrapping_add(1), Ordering::Release);
+            Some(data)
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.head.load(Ordering::Acquire) == self.tail.load(Ordering::Acquire)
+    }
+}
+
+impl Drop for SpscRing {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+
+/// Shared state between all senders and the receiver
+pub struct RingRegistry {
+    id: usize,
+    ring_capacity: usize,
+    rings: std::sync::Mutex<std::vec::Vec<Arc<SpscRing>>>,
+    senders: AtomicUsize,
+    is_receiver_alive: AtomicBool,
+    receiver_thread: std::sync::Mutex<Option<std::thread::Thread>>,
+    /// Messages lost without reaching a ring
+    dropped: AtomicU64,
+}
+
+impl RingRegistry {
+    pub fn new(ring_capacity: usize) -> Self {
+        Self {
+            id: NEXT_REGISTRY_ID.fetch_add(1, Ordering::Relaxed),
+            ring_capacity,
+            rings: std::sync::Mutex::new(vec!()),
+            senders: AtomicUsize::new(1),
+            is_receiver_alive: AtomicBool::new(true),
+            receiver_thread: std::sync::Mutex::new(None),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    fn lock_rings(&self) -> std::sync::MutexGuard<'_, std::vec::Vec<Arc<SpscRing>>> {
+        self.rings.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Ring of the calling thread, registered on first use
+    fn thread_ring(&self) -> Option<Arc<SpscRing>> {
+        THREAD_RINGS.try_with(|rings| {
+            let mut rings = rings.borrow_mut();
+            rings.retain(|(_, r)| !r.is_orphaned.load(Ordering::Relaxed));
+            if let Some((_, r)) = rings.iter().find(|(id, _)| *id == self.id) {
+                r.clone()
+            } else {
+                let r = Arc::new(SpscRing::new(self.ring_capacity));
+                self.lock_rings().push(r.clone());
+                rings.push((self.id, r.clone()));
+                r
+            }
+        }).ok()
+    }
+
+    /// Send from the calling thread
+    ///
+    /// Lock free once the thread has its ring.
+    /// Records are dropped and counted if the ring is full.
+    /// Commands are never dropped, the sender wakes the receiver and waits until there is room.
+    /// The receiver is woken for `Warn`, `Error` and commands, otherwise it picks up
+    /// messages on its next poll.
+    pub fn send(&self, data: Message) -> Result<Sent, SendError> {
+        if !self.is_receiver_alive.load(Ordering::Acquire) {
+            return Err(SendError(data));
+        }
+        let is_urgent = data.level() <= log_common::Level::Warn;
+
+        let ring = match self.thread_ring() {
+            Some(r) => r,
+            // Thread local storage already destroyed, thread is exiting
+            None if data.is_command() => return Err(SendError(data)),
+            None => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                return Ok(Sent::Dropped);
+            }
+        };
+
+        let mut data = data;
+        let ans = loop {
+            match ring.push(data) {
+                Ok(()) => break Sent::Queued,
+                Err(d) if d.is_command() => {
+                    self.wake_receiver();
+                    if !self.is_receiver_alive.load(Ordering::Acquire) {
+                        return Err(SendError(d));
+                    }
+                    std::thread::sleep(RETRY_INTERVAL);
+                    data = d;
+                }
+                Err(_) => {
+                    ring.dropped.fetch_add(1, Ordering::Relaxed);
+                    break Sent::Dropped;
+                }
+            }
+        };
+
+        if is_urgent {
+            self.wake_receiver();
+        }
+        Ok(ans)
+    }
+
+    fn wake_receiver(&self) {
+        if let Some(t) = self.receiver_thread.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            t.unpark();
+        }
+    }
+
+    pub fn add_sender(&self) {
+        self.senders.fetch_add(1, Ordering::AcqRel);
+    }
+
+    pub fn remove_sender(&self) {
+        if 1 == self.senders.fetch_sub(1, Ordering::AcqRel) {
+            self.wake_receiver();
+        }
+    }
+
+    pub fn close_receiver(&self) {
+        self.is_receiver_alive.store(false, Ordering::Release);
+    }
+
+    /// Move everything currently in the rings to `pending`, ordered by time stamp
+    ///
+    /// Commands take the time stamp of the record before them from the same ring,
+    /// so the order of messages from each thread is kept.
+    /// Rings of threads that have exited are removed once empty.
+    fn drain(&self, pending: &mut std::collections::VecDeque<Message>) {
+        let mut batch = std::vec::Vec::new();
+        {
+            let mut rings = self.lock_rings();
+            for r in rings.iter() {
+                let mut key = None;
+                while let Some(m) = r.pop() {
+                    if let Message::Record(d) = &m {
+                        key = Some(d.time_stamp());
+                    }
+                    batch.push((key, m));
+                }
+            }
+            rings.retain(|r| Arc::strong_count(r) > 1 || !r.is_empty());
+        }
+        batch.sort_by_key(|(key, _)| *key);
+        pending.extend(batch.into_iter().map(|(_, m)| m));
+    }
+
+    /// Wait up to `timeout` for a message
+    pub fn recv_timeout(
+        &self,
+        pending: &mut std::collections::VecDeque<Message>,
+        timeout: std::time::Duration,
+    ) -> Result<Message, RecvTimeoutError> {
+        {
+            let mut t = self.receiver_thread.lock().unwrap_or_else(|e| e.into_inner());
+            if t.is_none() {
+                *t = Some(std::thread::current());
+            }
+        }
+
+        let deadline = std::time::Instant::now() + timeout;
+        loop {
+            if pending.is_empty() {
+                self.drain(pending);
+            }
+            if let Some(d) = pending.pop_front() {
+                return Ok(d);
+            }
+            if 0 == self.senders.load(Ordering::Acquire) {
+                // Last check, a sender may have sent just before being dropped
+                self.drain(pending);
+                return pending.pop_front().ok_or(RecvTimeoutError::Disconnected);
+            }
+
+            let now = std::time::Instant::now();
+            if now >= deadline {
+                return Err(RecvTimeoutError::Timeout);
+            }
+            std::thread::park_timeout(POLL_INTERVAL.min(deadline - now));
+        }
+    }
+
+    /// Read and reset the number of messages dropped since the last call
+    pub fn take_dropped(&self) -> u64 {
+        let mut ans = self.dropped.swap(0, Ordering::Relaxed);
+        for r in self.lock_rings().iter() {
+            ans += r.dropped.swap(0, Ordering::Relaxed);
+        }
+        ans
+    }
+}
+
+impl Drop for RingRegistry {
+    fn drop(&mut self) {
+        for r in self.lock_rings().iter() {
+            r.is_orphaned.store(true, Ordering::Relaxed);
+        }
+    }
+}
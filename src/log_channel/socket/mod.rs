@@ -0,0 +1,50 @@
+// socket module
+//
+// Sending end of a channel to a receiver in another process over a Unix domain socket.
+
+use super::log_common::{self, wire};
+use super::{Message, SendError, Sent};
+
+/// Connection to a `LogDaemon`, shared by every clone of a sender
+pub struct SocketSender {
+    stream: std::sync::Mutex<std::os::unix::net::UnixStream>,
+}
+
+impl SocketSender {
+    pub fn connect(socket_path: &std::path::Path, origin: log_common::Origin) -> Result<Self, &'static str> {
+        let stream = std::os::unix::net::UnixStream::connect(socket_path)
+            .map_err(|_| "Failed to connect to log daemon socket")?;
+        Self::from_stream(stream, origin)
+    }
+
+    /// Use an already connected stream, such as one inherited from a parent process
+    ///
+    /// The origin is sent first, the daemon tags every following record with it.
+    pub fn from_stream(mut stream: std::os::unix::net::UnixStream, origin: log_common::Origin) -> Result<Self, &'static str> {
+        use std::io::Write;
+        stream.write_all(&wire::encode(&wire::Frame::Hello(origin)))
+            .map_err(|_| "Failed to write to log daemon socket")?;
+        Ok(Self { stream: std::sync::Mutex::new(stream) })
+    }
+
+    /// Send message as a frame
+    ///
+    /// Only records, shutdown and dump can cross the process boundary,
+    /// other commands fail as if the receiver were gone.
+    pub fn send(&self, data: Message) -> Result<Sent, SendError> {
+        let frame = match &data {
+            Message::Record(d) => wire::Frame::Record(d.clone()),
+            Message::Shutdown => wire::Frame::Shutdown,
+            Message::Dump(reason) => wire::Frame::Dump(reason.clone()),
+            _ => return Err(SendError(data)),
+        };
+
+        use std::io::Write;
+        let mut stream = self.stream.lock().unwrap_or_else(|e| e.into_inner());
+        if stream.write_all(&wire::encode(&frame)).is_err() {
+            Err(SendError(data))
+        } else {
+            Ok(Sent::Queued)
+        }
+    }
+}
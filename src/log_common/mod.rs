@@ -1,5 +1,17 @@
 // log common module
 
+pub mod wire;
+
+/// Name of the current thread, `None` if unnamed
+///
+/// Looked up once per thread and shared by every message it sends.
+pub fn get_thread_name() -> Option<std::sync::Arc<str>> {
+    thread_local! {
+        static THREAD_NAME: Option<std::sync::Arc<str>> = std::thread::current().name().map(std::sync::Arc::from);
+    }
+    THREAD_NAME.with(|n| n.clone())
+}
+
 /// Get current time from Chrono in Local timezone
 pub fn get_time_now() -> chrono::DateTime<chrono::offset::Local> {
     chrono::offset::Local::now()
@@ -25,11 +37,78 @@ fn pad_string(target_length: usize, line: &mut String) {
     }
 }
 
-#[derive(Clone)]
+/// Escape string for use inside a JSON string literal
+fn escape_json(line: &str) -> String {
+    let mut ans = String::with_capacity(line.len());
+    for c in line.chars() {
+        match c {
+            '"'  => ans.push_str("\\\""),
+            '\\' => ans.push_str("\\\\"),
+            '\n' => ans.push_str("\\n"),
+            '\r' => ans.push_str("\\r"),
+            '\t' => ans.push_str("\\t"),
+            c if (c as u32) < 0x20 => ans.push_str(&format!("\\u{:04x}", c as u32)),
+            c => ans.push(c),
+        }
+    }
+    ans
+}
+
+/// Source code location a log message was sent from
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceLocation {
+    pub file: std::borrow::Cow<'static, str>,
+    pub line: u32,
+}
+
+impl SourceLocation {
+    /// Location of the caller, see [`std::panic::Location::caller`]
+    #[track_caller]
+    pub fn caller() -> Self {
+        let l = std::panic::Location::caller();
+        Self {
+            file: std::borrow::Cow::Borrowed(l.file()),
+            line: l.line(),
+        }
+    }
+}
+
+/// Process a log message was sent from, when several processes log into one receiver
+#[derive(Clone, Debug, PartialEq)]
+pub struct Origin {
+    pub pid: u32,
+    pub process_name: String,
+}
+
+impl Origin {
+    /// The current process, named after its executable
+    pub fn current() -> Self {
+        let process_name = std::env::current_exe()
+            .ok()
+            .and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .unwrap_or_default();
+        Self::named(&process_name)
+    }
+
+    /// The current process, with the given name
+    pub fn named(process_name: &str) -> Self {
+        Self {
+            pid: std::process::id(),
+            process_name: process_name.to_string(),
+        }
+    }
+}
+
+/// A single log message
+#[derive(Clone, Debug)]
 pub struct LogData {
     level: Level,
     time_stamp: chrono::DateTime<chrono::offset::Local>,
     line: String,
+    thread: Option<std::sync::Arc<str>>,
+    location: Option<SourceLocation>,
+    fields: Box<[(String, String)]>, // Boxed slice keeps messages small in the channel
+    origin: Option<std::sync::Arc<Origin>>,
 }
 
 impl LogData {
@@ -44,28 +123,152 @@ impl LogData {
             level,
             time_stamp,
             line,
+            thread: None,
+            location: None,
+            fields: Box::default(),
+            origin: None,
         }
     }
 
+    /// Attach the name of the thread the message was sent from
+    pub fn with_thread(mut self, thread: std::sync::Arc<str>) -> Self {
+        self.thread = Some(thread);
+        self
+    }
+
+    /// Attach the source code location the message was sent from
+    pub fn with_location(mut self, location: SourceLocation) -> Self {
+        self.location = Some(location);
+        self
+    }
+
+    /// Attach custom key value fields
+    pub fn with_fields(mut self, fields: std::vec::Vec<(String, String)>) -> Self {
+        self.fields = fields.into_boxed_slice();
+        self
+    }
+
+    /// Attach the process the message was sent from, shared by every message of that process
+    pub fn with_origin(mut self, origin: std::sync::Arc<Origin>) -> Self {
+        self.origin = Some(origin);
+        self
+    }
+
     /// Read level of this log message
     pub fn level(&self) -> Level {
         self.level
     }
 
+    /// Read time the log message was created
+    pub fn time_stamp(&self) -> chrono::DateTime<chrono::offset::Local> {
+        self.time_stamp
+    }
+
+    /// Read payload line of this log message
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    /// Read name of the thread the message was sent from, if known
+    pub fn thread(&self) -> Option<&str> {
+        self.thread.as_deref()
+    }
+
+    /// Read source code location the message was sent from, if known
+    pub fn location(&self) -> Option<&SourceLocation> {
+        self.location.as_ref()
+    }
+
+    /// Read custom key value fields
+    pub fn fields(&self) -> &[(String, String)] {
+        &self.fields
+    }
+
+    /// Read process the message was sent from, if known
+    pub fn origin(&self) -> Option<&Origin> {
+        self.origin.as_deref()
+    }
+
     /// Read log message formatted into String
     /// 
     /// Output = "<timestamp> | <log level> | <payload line>"
+    /// 
+    /// If the origin is known, output = "<timestamp> | <log level> @ <process name>[<pid>] | <payload line>"
+    /// 
+    /// The origin is part of the level field so a payload can never be mistaken for it.
+    /// Spaces, `|` and new lines in the process name are written as `_`.
     pub fn as_string(&self) -> String {
+        self.format_string(self.time_stamp.to_rfc3339())
+    }
+
+    /// Same as [`Self::as_string`] with the time stamp written at UTC offset `offset`
+    /// 
+    /// Such as to keep the offset a record read back from a file was written with.
+    pub fn as_string_at(&self, offset: &chrono::FixedOffset) -> String {
+        self.format_string(self.time_stamp.with_timezone(offset).to_rfc3339())
+    }
+
+    fn format_string(&self, mut time_stamp: String) -> String {
         const EXPECTED_DATE_LENGTH: usize = 35;
-        let mut time_stamp = self.time_stamp.to_rfc3339();
         debug_assert!(time_stamp.len() <= EXPECTED_DATE_LENGTH);
         pad_string(EXPECTED_DATE_LENGTH, &mut time_stamp);
-        format!("{} | {} | {}\n", time_stamp, self.level, self.line) // return formatted string
+        match self.origin() {
+            Some(o) => {
+                // Name kept to one word so the line can be split on its separators when read back
+                let name = o.process_name.replace([' ', '|', '\n'], "_");
+                format!("{} | {} @ {}[{}] | {}\n", time_stamp, self.level, name, o.pid, self.line)
+            }
+            None => format!("{} | {} | {}\n", time_stamp, self.level, self.line), // return formatted string
+        }
+    }
+
+    /// Read log message formatted as a single line JSON object
+    /// 
+    /// Output = `{"timestamp":"<timestamp>","level":"<log level>","message":"<payload line>"}`
+    /// 
+    /// `"thread"` is added if the thread is named,
+    /// `"file"` and `"line"` if the location is known,
+    /// `"pid"` and `"process"` if the origin is known,
+    /// and `"fields"` as an object if there are custom fields.
+    pub fn as_json(&self) -> String {
+        self.format_json(self.time_stamp.to_rfc3339())
+    }
+
+    /// Same as [`Self::as_json`] with the time stamp written at UTC offset `offset`
+    pub fn as_json_at(&self, offset: &chrono::FixedOffset) -> String {
+        self.format_json(self.time_stamp.with_timezone(offset).to_rfc3339())
+    }
+
+    fn format_json(&self, time_stamp: String) -> String {
+        let mut ans = format!(
+            "{{\"timestamp\":\"{}\",\"level\":\"{}\",\"message\":\"{}\"",
+            time_stamp,
+            self.level.name(),
+            escape_json(&self.line)
+        );
+        if let Some(t) = self.thread() {
+            ans += &format!(",\"thread\":\"{}\"", escape_json(t));
+        }
+        if let Some(l) = self.location.as_ref() {
+            ans += &format!(",\"file\":\"{}\",\"line\":{}", escape_json(&l.file), l.line);
+        }
+        if let Some(o) = self.origin() {
+            ans += &format!(",\"pid\":{},\"process\":\"{}\"", o.pid, escape_json(&o.process_name));
+        }
+        if !self.fields.is_empty() {
+            let fields: std::vec::Vec<String> = self.fields
+                .iter()
+                .map(|(k, v)| format!("\"{}\":\"{}\"", escape_json(k), escape_json(v)))
+                .collect();
+            ans += &format!(",\"fields\":{{{}}}", fields.join(","));
+        }
+        ans += "}\n";
+        ans
     }
 }
 
 /// Support log levels
-#[derive(PartialEq, std::cmp::PartialOrd, Clone, Copy)]
+#[derive(PartialEq, std::cmp::PartialOrd, Clone, Copy, Debug)]
 pub enum Level {
     Trace = 4,
     Info  = 3,
@@ -74,6 +277,30 @@ pub enum Level {
     Off   = 0,
 }
 
+impl Level {
+    /// Level from its numeric value, values above `Trace` are treated as `Trace`
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Self::Off,
+            1 => Self::Error,
+            2 => Self::Warn,
+            3 => Self::Info,
+            _ => Self::Trace,
+        }
+    }
+
+    /// Name of level without padding
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Trace => "Trace",
+            Self::Info  => "Info",
+            Self::Warn  => "Warn",
+            Self::Error => "Error",
+            Self::Off   => "Off",
+        }
+    }
+}
+
 impl std::fmt::Display for Level {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -0,0 +1,207 @@
+// wire module
+//
+// Versioned binary encoding of messages sent between processes or over a network.
+//
+// Frame = u32 length of payload, big endian, then payload
+// Payload = u8 version, u8 kind, then for
+//   Record:   u8 level, i64 time stamp in nanoseconds since the epoch,
+//             u8 has thread, [str thread],
+//             u8 has location, [str file, u32 line],
+//             u32 field count, [str key, str value]...,
+//             str line,
+//             u8 has origin, [u32 pid, str process name]
+//   Shutdown: nothing
+//   Dump:     str reason
+//   Hello:    u32 pid, str process name
+// str = u32 length then UTF-8 bytes
+// All integers big endian.
+//
+// The version is bumped whenever the layout of a payload changes,
+// frames of an unknown version are rejected rather than misread.
+
+use super::{Level, LogData, Origin, SourceLocation};
+
+#[cfg(test)]
+mod wire_tests;
+
+/// Version of the encoding written by this crate
+pub const WIRE_VERSION: u8 = 1;
+
+/// Largest payload accepted, guards against reading garbage as a huge length
+const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+const KIND_RECORD: u8 = 1;
+const KIND_SHUTDOWN: u8 = 2;
+const KIND_DUMP: u8 = 3;
+const KIND_HELLO: u8 = 4;
+
+/// Message carried in one frame
+#[derive(Debug, Clone)]
+pub enum Frame {
+    Record(LogData),
+    /// Sender is shutting down cleanly
+    Shutdown,
+    /// Dump now, with the reason
+    Dump(String),
+    /// First frame on a connection, the process the following records come from
+    Hello(Origin),
+}
+
+/// Encode frame, including its length prefix
+pub fn encode(frame: &Frame) -> std::vec::Vec<u8> {
+    let mut payload = vec![WIRE_VERSION];
+    match frame {
+        Frame::Record(d) => {
+            payload.push(KIND_RECORD);
+            payload.push(d.level() as u8);
+            payload.extend_from_slice(&d.time_stamp().timestamp_nanos().to_be_bytes());
+            if let Some(t) = d.thread() {
+                payload.push(1);
+                put_str(&mut payload, t);
+            } else {
+                payload.push(0);
+            }
+            if let Some(l) = d.location() {
+                payload.push(1);
+                put_str(&mut payload, &l.file);
+                payload.extend_from_slice(&l.line.to_be_bytes());
+            } else {
+                payload.push(0);
+            }
+            payload.extend_from_slice(&(d.fields().len() as u32).to_be_bytes());
+            for (k, v) in d.fields() {
+                put_str(&mut payload, k);
+                put_str(&mut payload, v);
+            }
+            put_str(&mut payload, d.line());
+            if let Some(o) = d.origin() {
+                payload.push(1);
+                payload.extend_from_slice(&o.pid.to_be_bytes());
+                put_str(&mut payload, &o.process_name);
+            } else {
+                payload.push(0);
+            }
+        }
+        Frame::Shutdown => payload.push(KIND_SHUTDOWN),
+        Frame::Dump(reason) => {
+            payload.push(KIND_DUMP);
+            put_str(&mut payload, reason);
+        }
+        Frame::Hello(o) => {
+            payload.push(KIND_HELLO);
+            payload.extend_from_slice(&o.pid.to_be_bytes());
+            put_str(&mut payload, &o.process_name);
+        }
+    }
+
+    let mut ans = std::vec::Vec::with_capacity(4 + payload.len());
+    ans.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    ans.extend_from_slice(&payload);
+    ans
+}
+
+/// Read next frame
+///
+/// Returns `Ok(None)` if the stream ended cleanly between frames.
+pub fn read_frame(r: &mut impl std::io::Read) -> Result<Option<Frame>, &'static str> {
+    let mut len = [0_u8; 4];
+    match r.read_exact(&mut len) {
+        Ok(()) => (),
+        Err(e) if std::io::ErrorKind::UnexpectedEof == e.kind() => return Ok(None),
+        Err(_) => return Err("Failed to read frame"),
+    }
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME_SIZE {
+        return Err("Frame is too large");
+    }
+
+    let mut payload = vec![0_u8; len];
+    r.read_exact(&mut payload).map_err(|_| "Stream ended inside a frame")?;
+    decode(&payload).map(Some)
+}
+
+/// Decode payload of a frame
+fn decode(payload: &[u8]) -> Result<Frame, &'static str> {
+    let mut c = Cursor { bytes: payload, at: 0 };
+    if WIRE_VERSION != c.u8()? {
+        return Err("Unsupported wire version");
+    }
+    match c.u8()? {
+        KIND_RECORD => {
+            let level = c.u8()?;
+            if level > Level::Trace as u8 {
+                return Err("Invalid level");
+            }
+            let nanos = i64::from_be_bytes(c.take(8)?.try_into().unwrap());
+            let time_stamp = chrono::TimeZone::timestamp_nanos(&chrono::offset::Local, nanos);
+            let thread = if 1 == c.u8()? { Some(c.str()?) } else { None };
+            let location = if 1 == c.u8()? {
+                let file = c.str()?;
+                let line = c.u32()?;
+                Some(SourceLocation { file: std::borrow::Cow::Owned(file), line })
+            } else {
+                None
+            };
+            let count = c.u32()?;
+            let mut fields = std::vec::Vec::new();
+            for _k in 0..count {
+                fields.push((c.str()?, c.str()?));
+            }
+
+            let mut d = LogData::new(Level::from_u8(level), time_stamp, c.str()?).with_fields(fields);
+            if let Some(t) = thread {
+                d = d.with_thread(std::sync::Arc::from(t));
+            }
+            if let Some(l) = location {
+                d = d.with_location(l);
+            }
+            if 1 == c.u8()? {
+                let pid = c.u32()?;
+                d = d.with_origin(std::sync::Arc::new(Origin { pid, process_name: c.str()? }));
+            }
+            Ok(Frame::Record(d))
+        }
+        KIND_SHUTDOWN => Ok(Frame::Shutdown),
+        KIND_DUMP => Ok(Frame::Dump(c.str()?)),
+        KIND_HELLO => {
+            let pid = c.u32()?;
+            Ok(Frame::Hello(Origin { pid, process_name: c.str()? }))
+        }
+        _ => Err("Unknown frame kind"),
+    }
+}
+
+fn put_str(payload: &mut std::vec::Vec<u8>, s: &str) {
+    payload.extend_from_slice(&(s.len() as u32).to_be_bytes());
+    payload.extend_from_slice(s.as_bytes());
+}
+
+/// Reads values from a payload, failing if it is too short
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], &'static str> {
+        if self.bytes.len() - self.at < n {
+            Err("Frame is truncated")
+        } else {
+            self.at += n;
+            Ok(&self.bytes[self.at - n..self.at])
+        }
+    }
+
+    fn u8(&mut self) -> Result<u8, &'static str> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, &'static str> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn str(&mut self) -> Result<String, &'static str> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| "String is not UTF-8")
+    }
+}
@@ -0,0 +1,98 @@
+#![cfg(test)]
+
+use super::*;
+
+/// Decode the single frame in `bytes`
+fn round_trip(frame: &Frame) -> Frame {
+    let bytes = encode(frame);
+    let mut r = &bytes[..];
+    let ans = read_frame(&mut r).unwrap().unwrap();
+    assert!(r.is_empty());
+    ans
+}
+
+fn record(frame: Frame) -> LogData {
+    match frame {
+        Frame::Record(d) => d,
+        _ => panic!("Expected record"),
+    }
+}
+
+
+#[test]
+fn test_round_trip_record() {
+    let d = LogData::new(Level::Warn, crate::log_common::get_time_now(), "test line 1 ✓".to_string())
+        .with_thread(std::sync::Arc::from("worker"))
+        .with_location(SourceLocation { file: std::borrow::Cow::Borrowed("src/main.rs"), line: 42 })
+        .with_fields(vec!(("k1".to_string(), "v1".to_string()), ("k2".to_string(), "".to_string())))
+        .with_origin(std::sync::Arc::new(Origin { pid: 4242, process_name: "worker-1".to_string() }));
+    let ans = record(round_trip(&Frame::Record(d.clone())));
+    assert!(d.level() == ans.level());
+    assert!(d.time_stamp() == ans.time_stamp());
+    assert!(d.line() == ans.line());
+    assert!(Some("worker") == ans.thread());
+    assert!(d.location() == ans.location());
+    assert!(d.fields() == ans.fields());
+    assert!(d.origin() == ans.origin());
+    assert!(d.as_json() == ans.as_json());
+
+    // Optional parts left out
+    let d = LogData::new(Level::Trace, crate::log_common::get_time_now(), "".to_string());
+    let ans = record(round_trip(&Frame::Record(d.clone())));
+    assert!(Level::Trace == ans.level());
+    assert!(d.time_stamp() == ans.time_stamp());
+    assert!(ans.line().is_empty());
+    assert!(ans.thread().is_none());
+    assert!(ans.location().is_none());
+    assert!(ans.fields().is_empty());
+    assert!(ans.origin().is_none());
+}
+
+#[test]
+fn test_round_trip_commands() {
+    assert!(matches!(round_trip(&Frame::Shutdown), Frame::Shutdown));
+    assert!(matches!(round_trip(&Frame::Dump("reason".to_string())), Frame::Dump(r) if "reason" == r));
+    let o = Origin { pid: 7, process_name: "worker-2".to_string() };
+    assert!(matches!(round_trip(&Frame::Hello(o.clone())), Frame::Hello(ans) if o == ans));
+}
+
+#[test]
+fn test_stream_of_frames() {
+    let mut bytes = encode(&Frame::Dump("first".to_string()));
+    bytes.extend(encode(&Frame::Shutdown));
+    let mut r = &bytes[..];
+    assert!(matches!(read_frame(&mut r), Ok(Some(Frame::Dump(_)))));
+    assert!(matches!(read_frame(&mut r), Ok(Some(Frame::Shutdown))));
+    assert!(matches!(read_frame(&mut r), Ok(None))); // Clean end between frames
+}
+
+#[test]
+fn test_invalid_frames() {
+    let d = LogData::new(Level::Info, crate::log_common::get_time_now(), "test line 1".to_string());
+    let bytes = encode(&Frame::Record(d));
+
+    // Stream ends inside a frame
+    let mut r = &bytes[..bytes.len() - 1];
+    assert!(read_frame(&mut r).is_err());
+
+    // Length prefix claims more than the payload holds
+    let mut short = bytes.clone();
+    short.truncate(bytes.len() - 3);
+    let len = (short.len() - 4) as u32;
+    short[0..4].copy_from_slice(&len.to_be_bytes());
+    assert!(read_frame(&mut &short[..]).is_err());
+
+    // Unknown version
+    let mut newer = bytes.clone();
+    newer[4] = WIRE_VERSION + 1;
+    assert!(Err("Unsupported wire version") == read_frame(&mut &newer[..]).map(|_| ()));
+
+    // Unknown kind
+    let mut unknown = bytes.clone();
+    unknown[5] = 99;
+    assert!(read_frame(&mut &unknown[..]).is_err());
+
+    // Garbage length
+    let huge = (u32::MAX).to_be_bytes();
+    assert!(Err("Frame is too large") == read_frame(&mut &huge[..]).map(|_| ()));
+}
@@ -0,0 +1,103 @@
+#![cfg(test)]
+
+use super::*;
+
+use crate::log_receiver::BufferSize;
+use crate::log_receiver::Sink;
+use crate::log_receiver::circular_buffer::circular_buffer_tests::mock_writer;
+
+/// Time for the daemon to handle what was sent over the socket
+const SETTLE: std::time::Duration = std::time::Duration::from_millis(300);
+
+
+#[test]
+fn test_daemon() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(&rw_dir).join("test_daemon.txt");
+    let socket_fp = std::path::PathBuf::from(&rw_dir).join("test_daemon.sock");
+
+    let (mut mock_file, mock_writer) = mock_writer::get_mock_text_data_writer();
+    let builder = logger_builder::LoggerBuilder::new(log_common::Level::Trace, log_common::Level::Error, BufferSize::Size16)
+        .add_sink(Sink::new("mock", fp, Box::new(mock_writer)).with_formatter(crate::json_formatter()));
+    let daemon = LogDaemon::start(socket_fp.to_str().unwrap(), builder).unwrap();
+    assert!(LogDaemon::start(socket_fp.to_str().unwrap(), logger_builder::LoggerBuilder::new(
+        log_common::Level::Trace, log_common::Level::Error, BufferSize::Size16)).is_err());
+
+    // Error from the application dumps in the daemon
+    let log = LogDaemon::connect(socket_fp.to_str().unwrap(), log_common::Level::Info).unwrap();
+    log.trace_str("test line 1");
+    log.log_fields(log_common::Level::Info, "test line 2", &[("k", "v")]);
+    log.error_str("test line 3");
+    std::thread::sleep(SETTLE);
+    mock_file.process_queued_messages();
+    let lines = mock_file.get_mock_data();
+    assert!(3 == lines.len()); // header plus lines
+    assert!( lines[1].contains("test line 2") );
+    assert!( lines[1].contains("log_daemon_tests") ); // Location of the application's call
+    assert!( lines[1].contains("\"fields\":{\"k\":\"v\"}") );
+    assert!( lines[2].contains("test line 3") );
+
+    // Clean shutdown does not dump
+    log.info_str("test line 4");
+    log.shutdown();
+    std::thread::sleep(SETTLE);
+    mock_file.process_queued_messages();
+    assert!(3 == mock_file.get_mock_data().len());
+
+    // Application going away without shutdown dumps, as if killed
+    let log = LogDaemon::connect(socket_fp.to_str().unwrap(), log_common::Level::Info).unwrap();
+    log.info_str("test line 5");
+    drop(log);
+    std::thread::sleep(SETTLE);
+    mock_file.process_queued_messages();
+    let lines = mock_file.get_mock_data();
+    assert!(6 == lines.len());
+    assert!("\nDump requested: Client disconnected without shutdown\n" == lines[3]);
+    assert!( lines[4].contains("test line 4") );
+    assert!( lines[5].contains("test line 5") );
+
+    daemon.shutdown();
+    assert!(!socket_fp.exists());
+    assert!(LogDaemon::connect(socket_fp.to_str().unwrap(), log_common::Level::Info).is_err());
+}
+
+#[test]
+fn test_daemon_aggregates_processes() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(&rw_dir).join("test_daemon_aggregates_processes.txt");
+    let socket_fp = std::path::PathBuf::from(&rw_dir).join("test_daemon_aggregates_processes.sock");
+
+    let (mut mock_file, mock_writer) = mock_writer::get_mock_text_data_writer();
+    let builder = logger_builder::LoggerBuilder::new(log_common::Level::Trace, log_common::Level::Error, BufferSize::Size16)
+        .add_sink(Sink::new("mock", fp, Box::new(mock_writer)));
+    let daemon = LogDaemon::start(socket_fp.to_str().unwrap(), builder).unwrap();
+
+    // One worker connects by path, the other over a socket pair from the supervisor
+    let worker_1 = LogDaemon::connect_as(socket_fp.to_str().unwrap(), "worker-1", log_common::Level::Trace).unwrap();
+    let worker_2 = LogDaemon::connect_stream(daemon.pair().unwrap(), "worker-2", log_common::Level::Trace).unwrap();
+
+    daemon.sender().info_str("test line 1");
+    std::thread::sleep(SETTLE);
+    worker_1.info_str("test line 2");
+    std::thread::sleep(SETTLE);
+    worker_2.info_str("test line 3");
+    std::thread::sleep(SETTLE);
+
+    // Error in one worker dumps the history of all
+    worker_2.error_str("test line 4");
+    std::thread::sleep(SETTLE);
+    mock_file.process_queued_messages();
+    let lines = mock_file.get_mock_data();
+    let pid = std::process::id();
+    assert!(5 == lines.len()); // header plus lines
+    assert!( lines[1].ends_with(" | test line 1\n") ); // Supervisor's own records are not tagged
+    assert!( lines[2].ends_with(&format!(" @ worker-1[{}] | test line 2\n", pid)) );
+    assert!( lines[3].ends_with(&format!(" @ worker-2[{}] | test line 3\n", pid)) );
+    assert!( lines[4].ends_with(&format!(" @ worker-2[{}] | test line 4\n", pid)) );
+
+    worker_1.shutdown();
+    worker_2.shutdown();
+    daemon.shutdown();
+}
@@ -0,0 +1,163 @@
+// log daemon module
+//
+// Receiver running in a companion process, so buffered messages survive the application being killed.
+// Applications connect over a Unix domain socket and send encoded messages,
+// which are forwarded into a normal logger owned by the daemon.
+// Several processes may connect at once, such as a supervisor's workers,
+// their records are tagged with the pid and name of the process and interleave in one buffer.
+
+use super::log_common;
+use super::log_channel;
+use super::log_sender;
+use super::logger_builder;
+
+#[cfg(test)]
+mod log_daemon_tests;
+
+/// Reason written in the dump header when an application goes away without `shutdown()`
+const DISCONNECT_REASON: &str = "Client disconnected without shutdown";
+
+/// Receiver side of out of process logging
+///
+/// Owns a logger built from a [`logger_builder::LoggerBuilder`] and accepts applications
+/// connecting with [`LogDaemon::connect`].
+/// Each connection is served by its own thread,
+/// and records are tagged with the [`log_common::Origin`] the connection announced.
+/// If a connection closes without the application calling `LogSender::shutdown()`,
+/// such as when it is killed, the buffer is dumped.
+pub struct LogDaemon {
+    log: log_sender::LogSender,
+    socket_path: std::path::PathBuf,
+    is_running: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    accept_thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl LogDaemon {
+
+    /// Build the logger and listen on `socket_path`
+    ///
+    /// A socket file left by a previous daemon that is no longer listening is replaced.
+    pub fn start(socket_path: &str, builder: logger_builder::LoggerBuilder) -> Result<Self, &'static str> {
+        let path = std::path::PathBuf::from(socket_path);
+        if path.exists() {
+            if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+                return Err("Another log daemon is already listening on the socket");
+            }
+            std::fs::remove_file(&path).map_err(|_| "Failed to remove stale socket file")?;
+        }
+
+        let listener = std::os::unix::net::UnixListener::bind(&path)
+            .map_err(|_| "Failed to bind log daemon socket")?;
+        let log = builder.build()?;
+        let is_running = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(true));
+
+        let accept_log = log.clone();
+        let accept_is_running = is_running.clone();
+        let accept_thread = std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                if !accept_is_running.load(std::sync::atomic::Ordering::Acquire) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    let log = accept_log.clone();
+                    std::thread::spawn(move || serve(stream, log));
+                }
+            }
+        });
+
+        Ok(Self {
+            log,
+            socket_path: path,
+            is_running,
+            accept_thread: Some(accept_thread),
+        })
+    }
+
+    /// Connect an application to the daemon listening on `socket_path`
+    ///
+    /// Messages less severe than `store_log_level` are dropped before being sent.
+    /// Records, `dump_now()` and `shutdown()` reach the daemon.
+    /// Commands that need an answer, or change the daemon's settings, fail as if the receiver were gone.
+    /// Records are tagged with this process's pid and executable name.
+    pub fn connect(socket_path: &str, store_log_level: log_common::Level) -> Result<log_sender::LogSender, &'static str> {
+        let sender = log_channel::unix_socket(std::path::Path::new(socket_path), log_common::Origin::current())?;
+        Ok(log_sender::LogSender::new(sender, store_log_level))
+    }
+
+    /// As [`LogDaemon::connect`], tagging records with `process_name` instead of the executable name
+    pub fn connect_as(
+        socket_path: &str,
+        process_name: &str,
+        store_log_level: log_common::Level,
+    ) -> Result<log_sender::LogSender, &'static str> {
+        let sender = log_channel::unix_socket(std::path::Path::new(socket_path), log_common::Origin::named(process_name))?;
+        Ok(log_sender::LogSender::new(sender, store_log_level))
+    }
+
+    /// As [`LogDaemon::connect_as`], over a stream already connected to the daemon
+    ///
+    /// For a child process given one end of [`LogDaemon::pair`] by its supervisor.
+    pub fn connect_stream(
+        stream: std::os::unix::net::UnixStream,
+        process_name: &str,
+        store_log_level: log_common::Level,
+    ) -> Result<log_sender::LogSender, &'static str> {
+        let sender = log_channel::unix_stream(stream, log_common::Origin::named(process_name))?;
+        Ok(log_sender::LogSender::new(sender, store_log_level))
+    }
+
+    /// Connected socket pair, the daemon serves one end and the other is returned
+    ///
+    /// Lets a supervisor hand a connection to a worker without a socket path,
+    /// such as by passing it as the worker's stdin with `Stdio::from(OwnedFd::from(stream))`.
+    /// The worker then calls [`LogDaemon::connect_stream`] on it.
+    pub fn pair(&self) -> Result<std::os::unix::net::UnixStream, &'static str> {
+        let (ours, theirs) = std::os::unix::net::UnixStream::pair()
+            .map_err(|_| "Failed to create socket pair")?;
+        let log = self.log.clone();
+        std::thread::spawn(move || serve(ours, log));
+        Ok(theirs)
+    }
+
+    /// Sender of the daemon's own logger
+    pub fn sender(&self) -> &log_sender::LogSender {
+        &self.log
+    }
+
+    /// Stop accepting connections, remove the socket file and shut down the logger
+    pub fn shutdown(mut self) {
+        self.is_running.store(false, std::sync::atomic::Ordering::Release);
+        // Wake the accept thread so it sees the flag
+        let _ret = std::os::unix::net::UnixStream::connect(&self.socket_path);
+        if let Some(t) = self.accept_thread.take() {
+            let _ret = t.join();
+        }
+        let _ret = std::fs::remove_file(&self.socket_path);
+        self.log.shutdown();
+    }
+}
+
+
+/// Forward messages from one application until it shuts down or disconnects
+fn serve(mut stream: std::os::unix::net::UnixStream, log: log_sender::LogSender) {
+    let mut origin = None;
+    loop {
+        match log_common::wire::read_frame(&mut stream) {
+            Ok(Some(log_common::wire::Frame::Hello(o))) => origin = Some(std::sync::Arc::new(o)),
+            Ok(Some(log_common::wire::Frame::Record(d))) => match origin.as_ref() {
+                Some(o) if d.origin().is_none() => log.forward(d.with_origin(o.clone())),
+                _ => log.forward(d),
+            },
+            Ok(Some(log_common::wire::Frame::Dump(reason))) => log.dump_now(&reason),
+            Ok(Some(log_common::wire::Frame::Shutdown)) => break,
+            Ok(None) => {
+                log.dump_now(DISCONNECT_REASON);
+                break;
+            }
+            Err(e) => {
+                log.dump_now(&format!("{}: {}", DISCONNECT_REASON, e));
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,193 @@
+#![cfg(test)]
+
+use super::*;
+
+fn data(level: log_common::Level, line: &str) -> log_common::LogData {
+    log_common::LogData::new(level, log_common::get_time_now(), line.to_string())
+}
+
+
+#[test]
+fn test_parse_line() {
+    let d = data(log_common::Level::Warn, "test line 1 | with separator");
+    let ans = parse_line(&d.as_string()).unwrap();
+    assert!(log_common::Level::Warn == ans.level());
+    assert!(d.time_stamp() == ans.time_stamp());
+    assert!("test line 1 | with separator" == ans.line());
+    assert!(ans.origin().is_none());
+
+    let d = data(log_common::Level::Error, "test line 2")
+        .with_origin(std::sync::Arc::new(log_common::Origin { pid: 42, process_name: "worker-1".to_string() }));
+    let ans = parse_line(&d.as_string()).unwrap();
+    assert!("test line 2" == ans.line());
+    assert!(d.origin() == ans.origin());
+
+    // Name is written as one word so the line still parses
+    let d = data(log_common::Level::Error, "test line 3")
+        .with_origin(std::sync::Arc::new(log_common::Origin { pid: 7, process_name: "my worker | 2".to_string() }));
+    let ans = parse_line(&d.as_string()).unwrap();
+    assert!("test line 3" == ans.line());
+    assert!("my_worker___2" == ans.origin().unwrap().process_name);
+    assert!(7 == ans.origin().unwrap().pid);
+    let incidents = parse_dump(&format!("\n{}{}", data(log_common::Level::Info, "test line 4").as_string(), d.as_string()));
+    assert!(2 == incidents[0].records.len());
+    assert!(log_common::Level::Error == incidents[0].records[1].level());
+
+    // Payload shaped like an origin tag is not one
+    let d = data(log_common::Level::Info, "worker[3] | started");
+    let ans = parse_line(&d.as_string()).unwrap();
+    assert!("worker[3] | started" == ans.line());
+    assert!(ans.origin().is_none());
+
+    let d = data(log_common::Level::Trace, "test \"line\" 3\t\\ ✓")
+        .with_thread(std::sync::Arc::from("main"))
+        .with_location(log_common::SourceLocation { file: std::borrow::Cow::Borrowed("src/main.rs"), line: 7 })
+        .with_fields(vec!(("k".to_string(), "v\n".to_string())))
+        .with_origin(std::sync::Arc::new(log_common::Origin { pid: 42, process_name: "worker-1".to_string() }));
+    let ans = parse_line(&d.as_json()).unwrap();
+    assert!(d.as_json() == ans.as_json());
+    assert!(d.time_stamp() == ans.time_stamp());
+
+    assert!(parse_line("").is_none());
+    assert!(parse_line("not a record").is_none());
+    assert!(parse_line("2022-01-01T00:00:00+00:00 | Fatal | test line 4").is_none());
+    assert!(parse_line(&d.as_json()[..20]).is_none());
+}
+
+#[test]
+fn test_parse_dump() {
+    let contents = [
+        data(log_common::Level::Info, "streamed").as_string(),
+        "\n".to_string(),
+        "Recovered from previous crash\n".to_string(),
+        data(log_common::Level::Info, "test line 1").as_string(),
+        "\n".to_string(),
+        "Dump requested: manual\n".to_string(),
+        "Stats: sent=2\n".to_string(),
+        data(log_common::Level::Info, "test line 2\ncontinued").as_string(),
+        data(log_common::Level::Error, "test line 3").as_json(),
+        "\n".to_string(),
+        data(log_common::Level::Error, "test line 4").as_string(),
+        data(log_common::Level::Error, "test line 5").as_string()[..20].to_string(), // Cut short by a crash
+    ].concat();
+
+    let incidents = parse_dump(&contents);
+    assert!(4 == incidents.len());
+
+    assert!(incidents[0].reason.is_none());
+    assert!(!incidents[0].is_recovered);
+    assert!(1 == incidents[0].records.len());
+    assert!("streamed" == incidents[0].records[0].line());
+
+    assert!(incidents[1].is_recovered);
+    assert!(1 == incidents[1].records.len());
+
+    assert!(Some("manual") == incidents[2].reason.as_deref());
+    assert!(Some("sent=2") == incidents[2].stats.as_deref());
+    assert!(2 == incidents[2].records.len());
+    assert!("test line 2\ncontinued" == incidents[2].records[0].line());
+    assert!("test line 3" == incidents[2].records[1].line());
+    assert!(incidents[2].unparsed.is_empty());
+
+    assert!(1 == incidents[3].records.len());
+    assert!("test line 4" == incidents[3].records[0].line());
+    assert!(1 == incidents[3].unparsed.len());
+
+    assert!(parse_dump("").is_empty());
+    assert!(parse_dump("\n\n\n").is_empty());
+}
+
+#[test]
+fn test_read_dump_file() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(&rw_dir).join("test_read_dump_file.txt");
+    let fp_json = std::path::PathBuf::from(&rw_dir).join("test_read_dump_file.json");
+    let _ret = std::fs::remove_file(&fp);
+    let _ret = std::fs::remove_file(&fp_json);
+
+    let log = crate::LoggerBuilder::new(log_common::Level::Trace, log_common::Level::Error, crate::BufferSize::Size16)
+        .add_sink(crate::Sink::file(fp.to_str().unwrap()).unwrap())
+        .add_sink(crate::Sink::file(fp_json.to_str().unwrap()).unwrap().with_formatter(crate::json_formatter()))
+        .build()
+        .unwrap();
+    log.info_str("test line 1");
+    log.error_str("test line 2");
+    log.info_str("test line 3");
+    log.dump_now("manual");
+    log.flush().unwrap();
+    log.shutdown();
+
+    for f in [&fp, &fp_json] {
+        let incidents = read_dump_file(f.to_str().unwrap()).unwrap();
+        assert!(2 == incidents.len());
+        assert!(incidents[0].reason.is_none());
+        assert!(2 == incidents[0].records.len());
+        assert!("test line 2" == incidents[0].records[1].line());
+        assert!(log_common::Level::Error == incidents[0].records[1].level());
+        assert!(Some("manual") == incidents[1].reason.as_deref());
+        assert!(1 == incidents[1].records.len());
+        assert!("test line 3" == incidents[1].records[0].line());
+    }
+    assert!(read_dump_file(fp.with_extension("missing").to_str().unwrap()).is_err());
+}
+
+#[test]
+fn test_merge_dump_files() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp_1 = std::path::PathBuf::from(&rw_dir).join("test_merge_dump_files_1.txt");
+    let fp_2 = std::path::PathBuf::from(&rw_dir).join("test_merge_dump_files_2.json");
+
+    // Record and the offset it is written with
+    let at = |t: &str, line: &str| {
+        let t = chrono::DateTime::parse_from_rfc3339(t).unwrap();
+        let d = log_common::LogData::new(log_common::Level::Info, t.with_timezone(&chrono::offset::Local), line.to_string());
+        (d, *t.offset())
+    };
+    let text = |(d, offset): (log_common::LogData, chrono::FixedOffset)| d.as_string_at(&offset);
+    let json = |(d, offset): (log_common::LogData, chrono::FixedOffset)| d.as_json_at(&offset);
+    // Host 1 in UTC, host 2 at UTC+02:00, with a dump in each between
+    std::fs::write(&fp_1, [
+        "\n".to_string(),
+        text(at("2022-03-01T10:00:00+00:00", "test line 1")),
+        text(at("2022-03-01T10:00:02+00:00", "test line 3")),
+        "\n".to_string(),
+        text(at("2022-03-01T10:00:04+00:00", "test line 5")),
+    ].concat()).unwrap();
+    std::fs::write(&fp_2, [
+        "\n".to_string(),
+        json(at("2022-03-01T12:00:01+02:00", "test line 2")),
+        json(at("2022-03-01T12:00:04+02:00", "test line 6")),
+        json(at("2022-03-01T12:00:03+02:00", "test line 4")), // Out of order within its file
+    ].concat()).unwrap();
+
+    let fp_1 = fp_1.to_str().unwrap();
+    let fp_2 = fp_2.to_str().unwrap();
+    let merged = merge_dump_files(&[fp_1, fp_2]).unwrap();
+    let lines: std::vec::Vec<&str> = merged.iter().map(|m| m.record.line()).collect();
+    assert!(["test line 1", "test line 2", "test line 3", "test line 4", "test line 5", "test line 6"] == lines[..]);
+    assert!(fp_1 == merged[0].source);
+    assert!(fp_2 == merged[1].source);
+    assert!(fp_1 == merged[4].source); // Same instant as line 6, first file first
+    assert!(0 == merged[0].offset.local_minus_utc());
+    assert!(2 * 3600 == merged[1].offset.local_minus_utc());
+
+    assert!(merge_dump_files(&[fp_1, "missing.txt"]).is_err());
+}
+
+
+#[test]
+fn test_parse_dump_offsets() {
+    let d = data(log_common::Level::Info, "test line 1");
+    let east = chrono::FixedOffset::east(5 * 3600 + 1800);
+    let west = chrono::FixedOffset::west(8 * 3600);
+    let contents = format!("\n{}{}", d.as_string_at(&east), d.as_json_at(&west));
+
+    // Offset of each record is kept as written
+    let incidents = parse_dump(&contents);
+    assert!(2 == incidents[0].offsets.len());
+    assert!(east == incidents[0].offsets[0]);
+    assert!(west == incidents[0].offsets[1]);
+    assert!(d.as_string_at(&east) == incidents[0].records[0].as_string_at(&incidents[0].offsets[0]));
+}
@@ -0,0 +1,346 @@
+// log reader module
+//
+// Parses files written by the logger's sinks back into records.
+// A dump is a blank line, optional header lines, then one formatted record per line.
+// Records may be text, as written by `LogData::as_string`, or JSON, as written by `LogData::as_json`.
+
+use super::log_common;
+
+#[cfg(test)]
+mod log_reader_tests;
+
+const REASON_PREFIX: &str = "Dump requested: ";
+const STATS_PREFIX: &str = "Stats: ";
+const RECOVERED_HEADER: &str = "Recovered from previous crash";
+
+/// Records written by one dump
+#[derive(Debug, Clone, Default)]
+pub struct Incident {
+    /// Reason given with `LogSender::dump_now`, or by the logger such as for a disconnected client
+    pub reason: Option<String>,
+    /// Stats line written with `LoggerBuilder::stats_in_dump_header`
+    pub stats: Option<String>,
+    /// Lines recovered on start from a run that crashed
+    pub is_recovered: bool,
+    /// Records in the order written, oldest first
+    pub records: std::vec::Vec<log_common::LogData>,
+    /// UTC offset each record's time stamp was written with, one per record
+    pub offsets: std::vec::Vec<chrono::FixedOffset>,
+    /// Lines that could not be parsed, such as a final line cut short by a crash
+    pub unparsed: std::vec::Vec<String>,
+}
+
+impl Incident {
+    fn is_empty(&self) -> bool {
+        self.reason.is_none()
+            && self.stats.is_none()
+            && !self.is_recovered
+            && self.records.is_empty()
+            && self.unparsed.is_empty()
+    }
+}
+
+
+/// Record from one of several merged files
+#[derive(Debug, Clone)]
+pub struct MergedRecord {
+    /// Path of the file the record was read from
+    pub source: String,
+    /// UTC offset of the time stamp as written in the file
+    pub offset: chrono::FixedOffset,
+    pub record: log_common::LogData,
+}
+
+
+/// Read dump files at `paths` into one timeline, oldest first
+///
+/// Records are ordered by the instant they were logged, so files written with different UTC offsets interleave correctly.
+/// Records logged at the same instant keep the order of their files in `paths`, then their order within the file.
+pub fn merge_dump_files(paths: &[&str]) -> Result<std::vec::Vec<MergedRecord>, &'static str> {
+    let mut ans = std::vec::Vec::new();
+    for path in paths {
+        for incident in parse_dump(&read_file(path)?) {
+            ans.extend(incident.records.into_iter().zip(incident.offsets).map(|(record, offset)| MergedRecord {
+                source: path.to_string(),
+                offset,
+                record,
+            }));
+        }
+    }
+    // Stable sort keeps the order of equal time stamps
+    ans.sort_by_key(|m| m.record.time_stamp());
+    Ok(ans)
+}
+
+/// Read dump file at `path`, grouped by incident
+///
+/// Lines written by a streaming sink, before any dump header, form the first incident.
+pub fn read_dump_file(path: &str) -> Result<std::vec::Vec<Incident>, &'static str> {
+    Ok(parse_dump(&read_file(path)?))
+}
+
+fn read_file(path: &str) -> Result<String, &'static str> {
+    let bytes = std::fs::read(path).map_err(|_| "Failed to read dump file")?;
+    // Crash may have cut a character short
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Parse contents of a dump file, grouped by incident
+///
+/// Text and JSON records may be mixed.
+/// A text line that is not a record continues the line of the record before it,
+/// as happens when a payload contains a new line.
+pub fn parse_dump(contents: &str) -> std::vec::Vec<Incident> {
+    let mut incidents = std::vec::Vec::new();
+    let mut current = Incident::default();
+    let mut is_header = true;
+    let line_count = contents.split('\n').count();
+    for (k, line) in contents.split('\n').enumerate() {
+        // Final line without a new line was cut short, it cannot continue a record
+        let is_truncated = line_count == k + 1;
+        if line.is_empty() {
+            // Blank line starts the next dump
+            if !current.is_empty() {
+                incidents.push(std::mem::take(&mut current));
+            }
+            is_header = true;
+            continue;
+        }
+
+        if is_header {
+            if let Some(r) = line.strip_prefix(REASON_PREFIX) {
+                current.reason = Some(r.to_string());
+                continue;
+            } else if let Some(s) = line.strip_prefix(STATS_PREFIX) {
+                current.stats = Some(s.to_string());
+                continue;
+            } else if RECOVERED_HEADER == line {
+                current.is_recovered = true;
+                continue;
+            }
+        }
+        is_header = false;
+
+        if let Some((d, offset)) = parse_line_offset(line) {
+            current.records.push(d);
+            current.offsets.push(offset);
+        } else if is_truncated || line.starts_with('{') || current.records.is_empty() {
+            current.unparsed.push(line.to_string());
+        } else if let Some(last) = current.records.pop() {
+            let continued = format!("{}\n{}", last.line(), line);
+            current.records.push(with_line(last, continued));
+        }
+    }
+    if !current.is_empty() {
+        incidents.push(current);
+    }
+    incidents
+}
+
+/// Parse one formatted record, text or JSON
+pub fn parse_line(line: &str) -> Option<log_common::LogData> {
+    parse_line_offset(line).map(|(d, _)| d)
+}
+
+/// Record with the UTC offset its time stamp was written with
+fn parse_line_offset(line: &str) -> Option<(log_common::LogData, chrono::FixedOffset)> {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    if line.starts_with('{') {
+        parse_json_line(line)
+    } else {
+        parse_text_line(line)
+    }
+}
+
+
+/// "<timestamp> | <log level>[ @ <process name>[<pid>]] | <payload line>"
+fn parse_text_line(line: &str) -> Option<(log_common::LogData, chrono::FixedOffset)> {
+    let mut parts = line.splitn(3, " | ");
+    let time_stamp = chrono::DateTime::parse_from_rfc3339(parts.next()?.trim_end()).ok()?;
+    let level_field = parts.next()?;
+    let payload = parts.next()?;
+
+    let local = time_stamp.with_timezone(&chrono::offset::Local);
+    // Origin is only ever part of the level field, never of the payload
+    let d = match level_field.split_once(" @ ") {
+        Some((level, tag)) => log_common::LogData::new(parse_level(level.trim_end())?, local, payload.to_string())
+            .with_origin(std::sync::Arc::new(parse_origin_tag(tag)?)),
+        None => log_common::LogData::new(parse_level(level_field.trim_end())?, local, payload.to_string()),
+    };
+    Some((d, *time_stamp.offset()))
+}
+
+/// "<process name>[<pid>]"
+fn parse_origin_tag(tag: &str) -> Option<log_common::Origin> {
+    let (process_name, pid) = tag.strip_suffix(']')?.rsplit_once('[')?;
+    if process_name.is_empty() {
+        return None;
+    }
+    Some(log_common::Origin {
+        pid: pid.parse().ok()?,
+        process_name: process_name.to_string(),
+    })
+}
+
+/// `{"timestamp":"..","level":"..","message":"..",...}`, see `LogData::as_json`
+fn parse_json_line(line: &str) -> Option<(log_common::LogData, chrono::FixedOffset)> {
+    let mut p = JsonParser { chars: line.chars().peekable() };
+    let members = p.object()?;
+    p.skip_whitespace();
+    if p.chars.next().is_some() {
+        return None; // Trailing garbage
+    }
+
+    let get_str = |key: &str| members.iter().find_map(|(k, v)| match v {
+        JsonValue::Str(s) if k == key => Some(s.as_str()),
+        _ => None,
+    });
+    let get_num = |key: &str| members.iter().find_map(|(k, v)| match v {
+        JsonValue::Num(n) if k == key => n.parse::<u32>().ok(),
+        _ => None,
+    });
+
+    let time_stamp = chrono::DateTime::parse_from_rfc3339(get_str("timestamp")?).ok()?;
+    let level = parse_level(get_str("level")?)?;
+    let mut d = log_common::LogData::new(
+        level,
+        time_stamp.with_timezone(&chrono::offset::Local),
+        get_str("message")?.to_string(),
+    );
+    if let Some(t) = get_str("thread") {
+        d = d.with_thread(std::sync::Arc::from(t));
+    }
+    if let (Some(file), Some(line)) = (get_str("file"), get_num("line")) {
+        d = d.with_location(log_common::SourceLocation { file: std::borrow::Cow::Owned(file.to_string()), line });
+    }
+    if let (Some(pid), Some(process_name)) = (get_num("pid"), get_str("process")) {
+        d = d.with_origin(std::sync::Arc::new(log_common::Origin { pid, process_name: process_name.to_string() }));
+    }
+    let fields = members.into_iter().find_map(|(k, v)| match v {
+        JsonValue::Object(f) if "fields" == k => Some(f),
+        _ => None,
+    });
+    if let Some(f) = fields {
+        d = d.with_fields(f);
+    }
+    Some((d, *time_stamp.offset()))
+}
+
+fn parse_level(s: &str) -> Option<log_common::Level> {
+    match s {
+        "Trace" => Some(log_common::Level::Trace),
+        "Info"  => Some(log_common::Level::Info),
+        "Warn"  => Some(log_common::Level::Warn),
+        "Error" => Some(log_common::Level::Error),
+        _ => None,
+    }
+}
+
+/// Same message with the payload line replaced
+fn with_line(d: log_common::LogData, line: String) -> log_common::LogData {
+    let mut ans = log_common::LogData::new(d.level(), d.time_stamp(), line)
+        .with_fields(d.fields().to_vec());
+    if let Some(t) = d.thread() {
+        ans = ans.with_thread(std::sync::Arc::from(t));
+    }
+    if let Some(l) = d.location() {
+        ans = ans.with_location(l.clone());
+    }
+    if let Some(o) = d.origin() {
+        ans = ans.with_origin(std::sync::Arc::new(o.clone()));
+    }
+    ans
+}
+
+
+/// Values found in the JSON written by `LogData::as_json`
+enum JsonValue {
+    Str(String),
+    Num(String),
+    Object(std::vec::Vec<(String, String)>),
+}
+
+/// Parser for the flat JSON objects the logger writes, not general JSON
+struct JsonParser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl JsonParser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, c: char) -> Option<()> {
+        self.skip_whitespace();
+        self.chars.next_if_eq(&c).map(|_| ())
+    }
+
+    /// Object of string, number, or object of string values
+    fn object(&mut self) -> Option<std::vec::Vec<(String, JsonValue)>> {
+        self.expect('{')?;
+        let mut ans = std::vec::Vec::new();
+        if self.expect('}').is_some() {
+            return Some(ans);
+        }
+        loop {
+            let key = self.string()?;
+            self.expect(':')?;
+            self.skip_whitespace();
+            let value = match self.chars.peek()? {
+                '"' => JsonValue::Str(self.string()?),
+                '{' => JsonValue::Object(self.string_object()?),
+                _ => JsonValue::Num(self.number()?),
+            };
+            ans.push((key, value));
+            if self.expect(',').is_none() {
+                self.expect('}')?;
+                return Some(ans);
+            }
+        }
+    }
+
+    /// Object of string values
+    fn string_object(&mut self) -> Option<std::vec::Vec<(String, String)>> {
+        self.object()?
+            .into_iter()
+            .map(|(k, v)| match v {
+                JsonValue::Str(s) => Some((k, s)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn number(&mut self) -> Option<String> {
+        let mut ans = String::new();
+        while let Some(c) = self.chars.next_if(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+            ans.push(c);
+        }
+        if ans.is_empty() { None } else { Some(ans) }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        self.expect('"')?;
+        let mut ans = String::new();
+        loop {
+            match self.chars.next()? {
+                '"' => return Some(ans),
+                '\\' => match self.chars.next()? {
+                    '"' => ans.push('"'),
+                    '\\' => ans.push('\\'),
+                    '/' => ans.push('/'),
+                    'n' => ans.push('\n'),
+                    'r' => ans.push('\r'),
+                    't' => ans.push('\t'),
+                    'b' => ans.push('\u{8}'),
+                    'f' => ans.push('\u{c}'),
+                    'u' => {
+                        let hex: String = (0..4).map_while(|_| self.chars.next()).collect();
+                        ans.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                    }
+                    _ => return None,
+                },
+                c => ans.push(c),
+            }
+        }
+    }
+}
@@ -24,24 +24,18 @@ impl MockTextFile {
     fn new(rx: mpsc::Receiver::<String>) -> Self {
         Self {
             mock_data: vec!(),
-            rx: rx,
+            rx,
         }
     }
 
     pub fn process_queued_messages(&mut self){
-        let mut it = self.rx.try_iter();
-        loop {
-            if let Some(s) = it.next(){
-                self.mock_data.push(s);
-            }
-            else{
-                break;
-            }
+        for s in self.rx.try_iter() {
+            self.mock_data.push(s);
         }
     }
 
     pub fn get_mock_data(&self) -> &Vec::<String>{
-      return &self.mock_data;
+      &self.mock_data
     }
 }
 
@@ -56,7 +50,7 @@ impl MockDataWriter {
     fn new(tx: mpsc::Sender::<String>) -> Self {
         Self {
             file: None, 
-            tx: tx,
+            tx,
         }
     }
 }
@@ -90,6 +84,42 @@ impl TextDataWriter for MockDataWriter{
 }
 
 
+/// Writer that can never be opened
+pub struct FailingDataWriter;
+
+
+impl TextDataWriter for FailingDataWriter{
+    fn open(&mut self, _p: &std::path::Path) -> Result<(), std::io::Error> {
+        Err(std::io::Error::from(std::io::ErrorKind::PermissionDenied))
+    }
+
+    fn close(&mut self) {
+    }
+
+    fn write(&mut self, _line: &str) -> Result<(), std::io::Error> {
+        Err(std::io::Error::from(std::io::ErrorKind::NotConnected))
+    }
+}
+
+
+/// Writer with a bug, panicking on every write
+pub struct PanickingDataWriter;
+
+
+impl TextDataWriter for PanickingDataWriter{
+    fn open(&mut self, _p: &std::path::Path) -> Result<(), std::io::Error> {
+        Ok(())
+    }
+
+    fn close(&mut self) {
+    }
+
+    fn write(&mut self, _line: &str) -> Result<(), std::io::Error> {
+        panic!("writer bug");
+    }
+}
+
+
 // Extra catch if conditional compilation logic becomes broken
 // This file must only be included in test builds
 #[cfg( not(test) )]
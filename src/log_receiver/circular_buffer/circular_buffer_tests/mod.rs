@@ -24,7 +24,7 @@ fn test_init() {
 
     assert!(cb.get(0) == Some(1.to_string()));
     assert!(cb.get(1) == Some(2.to_string()));
-    assert!(cb.get(2) == None);
+    assert!(cb.get(2).is_none());
 }
 
 #[should_panic]
@@ -130,7 +130,7 @@ fn test_reset_fast() {
 
     mock_file.process_queued_messages();
     let f = mock_file.get_mock_data();
-    assert!(f.len() == 0);
+    assert!(f.is_empty());
 }
 
 #[test]
@@ -155,14 +155,14 @@ fn test_more_overflow() {
     for k in ((BUFFER_SIZE * 1000) - BUFFER_SIZE)..1000 * BUFFER_SIZE {
         assert!(cb.get(k) == Some(format!("{}", k)));
     }
-    assert!(cb.get(((BUFFER_SIZE * 1000) - BUFFER_SIZE) - 1) == None);
-    assert!(cb.get(BUFFER_SIZE * 1000) == None);
+    assert!(cb.get(((BUFFER_SIZE * 1000) - BUFFER_SIZE) - 1).is_none());
+    assert!(cb.get(BUFFER_SIZE * 1000).is_none());
 
-    assert!(cb.does_index_exist(((BUFFER_SIZE * 1000) - BUFFER_SIZE) - 1) == false);
-    assert!(cb.does_index_exist(BUFFER_SIZE * 1000) == false);
+    assert!(!cb.does_index_exist(((BUFFER_SIZE * 1000) - BUFFER_SIZE) - 1));
+    assert!(!cb.does_index_exist(BUFFER_SIZE * 1000));
 
-    assert!(cb.does_index_exist((BUFFER_SIZE * 1000) - BUFFER_SIZE) == true);
-    assert!(cb.does_index_exist((BUFFER_SIZE * 1000) - 1) == true);
+    assert!(cb.does_index_exist((BUFFER_SIZE * 1000) - BUFFER_SIZE));
+    assert!(cb.does_index_exist((BUFFER_SIZE * 1000) - 1));
 
     assert!(cb.get_min_external_index_in_buffer() == ((BUFFER_SIZE * 1000) - BUFFER_SIZE));
 
@@ -214,4 +214,31 @@ fn test_wrap_and_write() {
     }
 
     assert!(BUFFER_SIZE + HEADER_SIZE == mock_file_data.len());
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_snapshot() {
+    const BUFFER_SIZE: usize = 0x10;
+
+    let mut b: std::vec::Vec<String> = std::vec::Vec::<String>::with_capacity(BUFFER_SIZE);
+    for _ in 0..BUFFER_SIZE {
+        b.push(String::new());
+    }
+    let d = data_writer::DataWriter::new();
+
+    let mut cb = CircularStringsBuffer::new(b, d);
+    assert!(cb.snapshot().is_empty());
+
+    for k in 0..BUFFER_SIZE + 4 {
+        cb.push(format!("{}", k));
+    }
+
+    let lines = cb.snapshot();
+    assert!(BUFFER_SIZE == lines.len());
+    assert!("4" == lines[0]);
+    assert!(format!("{}", BUFFER_SIZE + 3) == lines[BUFFER_SIZE - 1]);
+
+    // Buffer is left untouched
+    assert!(lines == cb.snapshot());
+    assert!(BUFFER_SIZE == cb.get_qty_in_buffer());
+}
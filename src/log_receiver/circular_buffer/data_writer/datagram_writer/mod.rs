@@ -0,0 +1,59 @@
+// datagram_writer module
+
+use std::os::unix::net::UnixDatagram;
+
+use super::TextDataWriter;
+
+
+/// Writer that sends each line as one datagram to a Unix domain socket
+/// 
+/// The path passed to `open()` is the socket to send to, such as `/dev/log`.
+/// The dump header is not sent.
+pub struct DatagramWriter {
+    socket: Option<UnixDatagram>,
+}
+
+
+impl DatagramWriter {
+    pub fn new() -> Self {
+        Self { socket: None }
+    }
+}
+
+
+impl Default for DatagramWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+impl TextDataWriter for DatagramWriter {
+
+    fn open(&mut self, p: &std::path::Path) -> Result<(), std::io::Error> {
+        if self.socket.is_some() {
+            Err(std::io::Error::from(std::io::ErrorKind::AlreadyExists))
+        } else {
+            let socket = UnixDatagram::unbound()?;
+            socket.connect(p)?;
+            self.socket = Some(socket);
+            Ok(())
+        }
+    }
+
+    fn close(&mut self) {
+        self.socket = None;
+    }
+
+    fn write(&mut self, line: &str) -> Result<(), std::io::Error> {
+        if let Some(s) = self.socket.as_ref() {
+            s.send(line.as_bytes()).map(|_| ())
+        } else {
+            Err(std::io::Error::from(std::io::ErrorKind::NotConnected))
+        }
+    }
+
+    fn write_header(&mut self, _header: &str) -> Result<(), std::io::Error> {
+        Ok(())
+    }
+}
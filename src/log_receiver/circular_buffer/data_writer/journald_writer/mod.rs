@@ -0,0 +1,108 @@
+// journald_writer module
+
+use std::os::unix::net::UnixDatagram;
+
+use super::TextDataWriter;
+use super::super::super::sink::journald::FIELD_SEPARATOR;
+
+
+/// Writer sending each line as one entry using the journald native protocol
+/// 
+/// Lines are expected from `journald::formatter()`.
+/// Every entry written between `open()` and `close()` gets the same `INCIDENT_ID`,
+/// tying together the messages of a single dump.
+/// The dump header is not sent.
+pub struct JournaldWriter {
+    socket: Option<UnixDatagram>,
+    incident_id: String,
+    incident_count: u32,
+}
+
+
+impl JournaldWriter {
+    pub fn new() -> Self {
+        Self {
+            socket: None,
+            incident_id: String::new(),
+            incident_count: 0,
+        }
+    }
+
+    /// Identifier of the dump currently being written
+    pub fn incident_id(&self) -> &str {
+        &self.incident_id
+    }
+
+    /// Make an identifier unique to this process and dump
+    fn next_incident_id(&mut self) -> String {
+        self.incident_count = self.incident_count.wrapping_add(1);
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        format!("{:016x}{:08x}{:08x}", nanos, std::process::id(), self.incident_count)
+    }
+}
+
+
+impl Default for JournaldWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+/// Encode one `NAME=value` field in the native protocol
+/// 
+/// Values containing a new line use the length prefixed binary form.
+pub fn encode_field(out: &mut std::vec::Vec<u8>, field: &str) {
+    let (name, value) = field.split_once('=').unwrap_or((field, ""));
+    if value.contains('\n') {
+        out.extend_from_slice(name.as_bytes());
+        out.push(b'\n');
+        out.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        out.extend_from_slice(value.as_bytes());
+    } else {
+        out.extend_from_slice(field.as_bytes());
+    }
+    out.push(b'\n');
+}
+
+
+impl TextDataWriter for JournaldWriter {
+
+    fn open(&mut self, p: &std::path::Path) -> Result<(), std::io::Error> {
+        if self.socket.is_some() {
+            Err(std::io::Error::from(std::io::ErrorKind::AlreadyExists))
+        } else {
+            let socket = UnixDatagram::unbound()?;
+            socket.connect(p)?;
+            self.socket = Some(socket);
+            self.incident_id = self.next_incident_id();
+            Ok(())
+        }
+    }
+
+    fn close(&mut self) {
+        self.socket = None;
+    }
+
+    fn write(&mut self, line: &str) -> Result<(), std::io::Error> {
+        if let Some(s) = self.socket.as_ref() {
+            let mut entry = std::vec::Vec::<u8>::with_capacity(line.len() + 64);
+            for field in line.split(FIELD_SEPARATOR) {
+                if !field.is_empty() {
+                    encode_field(&mut entry, field);
+                }
+            }
+            encode_field(&mut entry, &format!("INCIDENT_ID={}", self.incident_id));
+            s.send(&entry).map(|_| ())
+        } else {
+            Err(std::io::Error::from(std::io::ErrorKind::NotConnected))
+        }
+    }
+
+    fn write_header(&mut self, _header: &str) -> Result<(), std::io::Error> {
+        Ok(())
+    }
+}
@@ -5,12 +5,67 @@ pub trait TextDataWriter {
     fn open(&mut self, p: &std::path::Path) -> Result<(), std::io::Error>;
     fn close(&mut self);
     fn write(&mut self, line: &str) -> Result<(), std::io::Error>;
+
+    /// Write the separator placed before each data dump
+    /// 
+    /// Writers for message based destinations can override this to skip it.
+    fn write_header(&mut self, header: &str) -> Result<(), std::io::Error> {
+        self.write(header)
+    }
+
+    /// Lines accepted by `write()` and later discarded without being delivered
+    fn dropped(&self) -> u64 {
+        0
+    }
+
+    /// Bytes of lines accepted by `write()` that have not been delivered, dropped or still waiting
+    fn undelivered_bytes(&self) -> u64 {
+        0
+    }
+}
+
+impl<W: TextDataWriter + ?Sized> TextDataWriter for Box<W> {
+    fn open(&mut self, p: &std::path::Path) -> Result<(), std::io::Error> {
+        (**self).open(p)
+    }
+
+    fn close(&mut self) {
+        (**self).close()
+    }
+
+    fn write(&mut self, line: &str) -> Result<(), std::io::Error> {
+        (**self).write(line)
+    }
+
+    fn write_header(&mut self, header: &str) -> Result<(), std::io::Error> {
+        (**self).write_header(header)
+    }
+
+    fn dropped(&self) -> u64 {
+        (**self).dropped()
+    }
+
+    fn undelivered_bytes(&self) -> u64 {
+        (**self).undelivered_bytes()
+    }
 }
 
 mod writer;
+mod stderr_writer;
+mod network_writer;
+#[cfg(unix)]
+mod datagram_writer;
+#[cfg(unix)]
+mod journald_writer;
 
 
 
 pub use writer::DataWriter as DataWriter;
+pub use stderr_writer::StderrWriter as StderrWriter;
+pub use network_writer::{NetworkWriter, NetworkProtocol};
+#[cfg(unix)]
+pub use datagram_writer::DatagramWriter as DatagramWriter;
+#[cfg(unix)]
+pub use journald_writer::JournaldWriter as JournaldWriter;
 
 
@@ -0,0 +1,247 @@
+// network_writer module
+
+use std::io::prelude::*;
+use std::net::ToSocketAddrs;
+
+use super::TextDataWriter;
+
+
+/// How long to wait when connecting or sending before giving up
+const NETWORK_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(1000);
+
+/// Wait after a failed connect before trying again, doubled on each failure up to `MAX_RECONNECT_BACKOFF`
+const MIN_RECONNECT_BACKOFF: std::time::Duration = std::time::Duration::from_millis(250);
+const MAX_RECONNECT_BACKOFF: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Transport used by [`NetworkWriter`]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NetworkProtocol {
+    /// Each line is framed with a 4 byte big endian length prefix
+    Tcp,
+    /// Each line is sent as one datagram
+    Udp,
+}
+
+
+/// Writer that ships each line to a collector over the network
+/// 
+/// The path passed to `open()` is the collector address, such as `"10.0.0.5:5140"`.
+/// 
+/// For TCP the connection is kept open between dumps and re-established when it drops,
+/// either on the next dump or, for a streaming sink, on the next message.
+/// Frames that could not be sent, including whole dumps made while the collector is unreachable,
+/// are held in a bounded outbox and resent on a later dump once the connection is back.
+/// When the outbox is full the oldest frame is dropped, reported through [`TextDataWriter::dropped`].
+/// After a failed connect, dumps and messages do not try again until a backoff has passed,
+/// so an unreachable collector does not stall the receiver on every dump.
+/// The dump header is not sent.
+pub struct NetworkWriter {
+    protocol: NetworkProtocol,
+    /// Collector address given to the last `open()`
+    address: Option<String>,
+    tcp: Option<std::net::TcpStream>,
+    udp: Option<std::net::UdpSocket>,
+    outbox: std::collections::VecDeque<std::vec::Vec<u8>>,
+    outbox_capacity: usize,
+    dropped: u64,
+    dropped_bytes: u64,
+    is_open: bool,
+    next_connect: Option<std::time::Instant>,
+    backoff: std::time::Duration,
+}
+
+
+impl NetworkWriter {
+    /// `outbox_capacity` is the maximum number of unsent TCP frames kept
+    pub fn new(protocol: NetworkProtocol, outbox_capacity: usize) -> Self {
+        Self {
+            protocol,
+            address: None,
+            tcp: None,
+            udp: None,
+            outbox: std::collections::VecDeque::new(),
+            outbox_capacity,
+            dropped: 0,
+            dropped_bytes: 0,
+            is_open: false,
+            next_connect: None,
+            backoff: MIN_RECONNECT_BACKOFF,
+        }
+    }
+
+    /// Number of frames waiting to be sent
+    pub fn outbox_len(&self) -> usize {
+        self.outbox.len()
+    }
+
+    /// Check an idle connection has not been closed by the collector
+    fn is_tcp_alive(stream: &std::net::TcpStream) -> bool {
+        let mut buf = [0_u8; 1];
+        if stream.set_nonblocking(true).is_err() {
+            return false;
+        }
+        let ans = match stream.peek(&mut buf) {
+            Ok(0) => false, // Closed by other end
+            Ok(_) => true,
+            Err(e) => std::io::ErrorKind::WouldBlock == e.kind(),
+        };
+        ans && stream.set_nonblocking(false).is_ok()
+    }
+
+    fn connect_tcp(address: &str) -> Result<std::net::TcpStream, std::io::Error> {
+        let mut last_error = std::io::Error::from(std::io::ErrorKind::AddrNotAvailable);
+        for a in address.to_socket_addrs()? {
+            match std::net::TcpStream::connect_timeout(&a, NETWORK_TIMEOUT) {
+                Ok(s) => {
+                    s.set_write_timeout(Some(NETWORK_TIMEOUT))?;
+                    s.set_nodelay(true)?;
+                    return Ok(s);
+                }
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Connect unless connected, or a recent attempt failed and the backoff has not passed
+    fn reconnect_tcp(&mut self, address: &str) {
+        if self.tcp.as_ref().map(Self::is_tcp_alive).unwrap_or(false) {
+            return;
+        }
+        self.tcp = None;
+        if self.next_connect.is_some_and(|t| std::time::Instant::now() < t) {
+            return;
+        }
+        match Self::connect_tcp(address) {
+            Ok(s) => {
+                self.tcp = Some(s);
+                self.next_connect = None;
+                self.backoff = MIN_RECONNECT_BACKOFF;
+            }
+            Err(_) => {
+                self.next_connect = Some(std::time::Instant::now() + self.backoff);
+                self.backoff = std::cmp::min(self.backoff * 2, MAX_RECONNECT_BACKOFF);
+            }
+        }
+    }
+
+    fn connect_udp(address: &str) -> Result<std::net::UdpSocket, std::io::Error> {
+        let mut last_error = std::io::Error::from(std::io::ErrorKind::AddrNotAvailable);
+        for a in address.to_socket_addrs()? {
+            let local = if a.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+            match std::net::UdpSocket::bind(local).and_then(|s| s.connect(a).map(|_| s)) {
+                Ok(s) => return Ok(s),
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Send queued frames until the outbox is empty or sending fails
+    fn flush_outbox(&mut self) -> Result<(), std::io::Error> {
+        while let Some(frame) = self.outbox.front() {
+            if let Some(s) = self.tcp.as_mut() {
+                if let Err(e) = s.write_all(frame) {
+                    self.tcp = None; // Reconnect on next open() or write()
+                    return Err(e);
+                }
+                self.outbox.pop_front();
+            } else {
+                return Err(std::io::Error::from(std::io::ErrorKind::NotConnected));
+            }
+        }
+        Ok(())
+    }
+
+    fn enqueue(&mut self, frame: std::vec::Vec<u8>) {
+        self.outbox.push_back(frame);
+        while self.outbox.len() > self.outbox_capacity {
+            if let Some(f) = self.outbox.pop_front() {
+                self.dropped += 1;
+                self.dropped_bytes += Self::line_len(&f);
+            }
+        }
+    }
+
+    /// Length of the line in a frame, without the length prefix
+    fn line_len(frame: &[u8]) -> u64 {
+        frame.len().saturating_sub(4) as u64
+    }
+}
+
+
+impl TextDataWriter for NetworkWriter {
+
+    fn open(&mut self, p: &std::path::Path) -> Result<(), std::io::Error> {
+        if self.is_open {
+            return Err(std::io::Error::from(std::io::ErrorKind::AlreadyExists));
+        }
+        let address = p.to_str().ok_or_else(|| std::io::Error::from(std::io::ErrorKind::InvalidInput))?;
+        self.address = Some(address.to_string());
+
+        match self.protocol {
+            NetworkProtocol::Tcp => {
+                // While disconnected the dump is queued in the outbox instead of failing
+                self.reconnect_tcp(address);
+                let _r = self.flush_outbox();
+            }
+            NetworkProtocol::Udp => {
+                if self.udp.is_none() {
+                    self.udp = Some(Self::connect_udp(address)?);
+                }
+            }
+        }
+        self.is_open = true;
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        if NetworkProtocol::Tcp == self.protocol {
+            let _r = self.flush_outbox();
+        }
+        self.is_open = false;
+    }
+
+    fn write(&mut self, line: &str) -> Result<(), std::io::Error> {
+        if !self.is_open {
+            return Err(std::io::Error::from(std::io::ErrorKind::NotConnected));
+        }
+        match self.protocol {
+            NetworkProtocol::Tcp => {
+                let mut frame = std::vec::Vec::<u8>::with_capacity(line.len() + 4);
+                frame.extend_from_slice(&(line.len() as u32).to_be_bytes());
+                frame.extend_from_slice(line.as_bytes());
+                self.enqueue(frame);
+                if self.tcp.is_none() {
+                    if let Some(address) = self.address.clone() {
+                        self.reconnect_tcp(&address);
+                    }
+                }
+                // Frame stays queued if the connection dropped, so it is not an error here
+                let _r = self.flush_outbox();
+                Ok(())
+            }
+            NetworkProtocol::Udp => {
+                if let Some(s) = self.udp.as_ref() {
+                    s.send(line.as_bytes()).map(|_| ())
+                } else {
+                    Err(std::io::Error::from(std::io::ErrorKind::NotConnected))
+                }
+            }
+        }
+    }
+
+    fn write_header(&mut self, _header: &str) -> Result<(), std::io::Error> {
+        Ok(())
+    }
+
+    /// Frames dropped because the outbox was full
+    fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Bytes of lines dropped from the outbox or still waiting in it
+    fn undelivered_bytes(&self) -> u64 {
+        self.dropped_bytes + self.outbox.iter().map(|f| Self::line_len(f)).sum::<u64>()
+    }
+}
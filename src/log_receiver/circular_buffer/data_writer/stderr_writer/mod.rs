@@ -0,0 +1,55 @@
+// stderr_writer module
+
+use std::io::prelude::*;
+
+use super::TextDataWriter;
+
+
+/// Writer that sends dumped lines to the standard error stream
+/// 
+/// The path passed to `open()` is ignored.
+pub struct StderrWriter {
+    stderr: Option<std::io::Stderr>,
+}
+
+
+impl StderrWriter {
+    pub fn new() -> Self {
+        Self { stderr: None }
+    }
+}
+
+
+impl Default for StderrWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+impl TextDataWriter for StderrWriter {
+
+    fn open(&mut self, _p: &std::path::Path) -> Result<(), std::io::Error> {
+        if self.stderr.is_some() {
+            Err(std::io::Error::from(std::io::ErrorKind::AlreadyExists))
+        } else {
+            self.stderr = Some(std::io::stderr());
+            Ok(())
+        }
+    }
+
+    fn close(&mut self) {
+        if let Some(s) = self.stderr.as_mut() {
+            let _r = s.flush();
+        }
+        self.stderr = None;
+    }
+
+    fn write(&mut self, line: &str) -> Result<(), std::io::Error> {
+        if let Some(s) = self.stderr.as_mut() {
+            s.write_all(line.as_bytes())
+        } else {
+            Err(std::io::Error::from(std::io::ErrorKind::NotConnected))
+        }
+    }
+}
@@ -18,6 +18,13 @@ impl DataWriter {
 }
 
 
+impl Default for DataWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
 impl TextDataWriter for DataWriter{
 
     fn open(&mut self, p: &std::path::Path) -> Result<(), std::io::Error> {
@@ -25,7 +32,6 @@ impl TextDataWriter for DataWriter{
             Err(std::io::Error::from(std::io::ErrorKind::AlreadyExists))
         } else {
             let f = OpenOptions::new()
-                .write(true)
                 .append(true)
                 .create(true)
                 .open(p);
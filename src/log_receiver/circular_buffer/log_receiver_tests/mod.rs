@@ -1,34 +1,26 @@
 #![cfg(test)]
 
 use super::super::log_common;
+use super::super::log_channel;
 use super::super::super::log_receiver::BufferSize;
 
 use super::super::circular_buffer::circular_buffer_tests::mock_writer;
 use super::super::circular_buffer::circular_buffer_tests::mock_writer::MockTextFile;
-use super::super::circular_buffer;
+use super::super::sink::Sink;
+use super::super::circular_buffer::mmap_ring::MmapRing;
 use super::super::LogReceiver;
 
 fn spawn_mocked(
     log_dump_level: log_common::Level,
-    receiver: std::sync::mpsc::Receiver<log_common::LogData>,
+    receiver: std::sync::mpsc::Receiver<log_channel::Message>,
     log_file_path: std::path::PathBuf,
     buffer_size: BufferSize,
 ) -> Option<MockTextFile> {
     if log_common::Level::Off != log_dump_level {
-        debug_assert!( buffer_size.is_valid() );
-
-        let mut buffer = std::vec::Vec::<String>::with_capacity(buffer_size.value());
-        for _k in 0..buffer_size.value() {
-            buffer.push(String::new());
-        }
-
         let (mock_file, text_data_writer) = mock_writer::get_mock_text_data_writer();
-        let circle = circular_buffer::CircularStringsBuffer::new(buffer, text_data_writer);
+        let sink = Sink::new("mock", log_file_path, Box::new(text_data_writer));
 
-        std::thread::spawn(move || {    
-            let mut logger = LogReceiver::new(log_dump_level, receiver, log_file_path, circle);
-            logger.execute();
-        });
+        spawn_mocked_sinks(log_dump_level, receiver, vec!(sink), buffer_size);
         Some(mock_file) // return mock file
 
     } else {
@@ -36,6 +28,33 @@ fn spawn_mocked(
     }
 }
 
+fn spawn_mocked_sinks(
+    log_dump_level: log_common::Level,
+    receiver: impl Into<log_channel::ChannelReceiver>,
+    sinks: std::vec::Vec<Sink>,
+    buffer_size: BufferSize,
+) {
+    debug_assert!( buffer_size.is_valid() );
+
+    let active_sinks = sinks.into_iter().map(|s| s.activate(buffer_size).unwrap()).collect();
+    let receiver = receiver.into();
+
+    std::thread::spawn(move || {    
+        let mut logger = LogReceiver::new(log_dump_level, receiver, active_sinks);
+        logger.execute();
+    });
+}
+
+/// Send `Shutdown` until the receiver thread exits
+fn shutdown_and_wait(sender: &std::sync::mpsc::Sender<log_channel::Message>) {
+    for _k in 0..100 {
+        if sender.send(log_channel::Message::Shutdown).is_err() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+}
+
 
 #[test]
 fn test_init_off() {
@@ -44,14 +63,12 @@ fn test_init_off() {
     let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
     let fp = std::path::PathBuf::from(rw_dir).join("test_init_off.txt");
 
-    let (sender, receiver) = std::sync::mpsc::channel::<log_common::LogData>();
+    let (sender, receiver) = std::sync::mpsc::channel::<log_channel::Message>();
 
     let _mock_file = spawn_mocked(log_common::Level::Off, receiver, fp, BufferSize::Size128);
     std::thread::sleep(std::time::Duration::from_millis(100));
 
-    let ts = log_common::get_time_now();
-    let d = log_common::LogData::new(log_common::Level::Off, ts, format!("Off line"));
-    assert!(sender.send(d).is_err());
+    assert!(sender.send(log_channel::Message::Shutdown).is_err());
 }
 
 
@@ -61,29 +78,27 @@ fn test_init_error() {
     let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
     let fp = std::path::PathBuf::from(rw_dir).join("test_init_error.txt");
 
-    let (sender, receiver) = std::sync::mpsc::channel::<log_common::LogData>();
+    let (sender, receiver) = std::sync::mpsc::channel::<log_channel::Message>();
 
     let mut mock_file = spawn_mocked(log_common::Level::Error, receiver, fp, BufferSize::Size128).unwrap();
 
     let ts = log_common::get_time_now();
-    let d = log_common::LogData::new(log_common::Level::Info, ts, format!("test line 1"));
-    assert!(sender.send(d).is_ok());
+    let d = log_common::LogData::new(log_common::Level::Info, ts, "test line 1".to_string());
+    assert!(sender.send(d.into()).is_ok());
 
     let ts = log_common::get_time_now();
-    let d = log_common::LogData::new(log_common::Level::Info, ts, format!("test line 2"));
-    assert!(sender.send(d).is_ok());
+    let d = log_common::LogData::new(log_common::Level::Info, ts, "test line 2".to_string());
+    assert!(sender.send(d.into()).is_ok());
 
     let ts = log_common::get_time_now();
-    let d = log_common::LogData::new(log_common::Level::Info, ts, format!("test line 3"));
-    assert!(sender.send(d).is_ok());
+    let d = log_common::LogData::new(log_common::Level::Info, ts, "test line 3".to_string());
+    assert!(sender.send(d.into()).is_ok());
 
-    let ts = log_common::get_time_now();
-    let d = log_common::LogData::new(log_common::Level::Off, ts, format!("Off line"));
-    assert!(sender.send(d).is_ok());
+    assert!(sender.send(log_channel::Message::Shutdown).is_ok());
 
     mock_file.process_queued_messages();
     let lines = mock_file.get_mock_data();
-    assert!(0 == lines.len());
+    assert!(lines.is_empty());
 }
 
 
@@ -93,36 +108,32 @@ fn test_write_error() {
     let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
     let fp = std::path::PathBuf::from(rw_dir).join("test_write_error.txt");
 
-    let (sender, receiver) = std::sync::mpsc::channel::<log_common::LogData>();
+    let (sender, receiver) = std::sync::mpsc::channel::<log_channel::Message>();
 
     let mut mock_file = spawn_mocked(log_common::Level::Error, receiver, fp, BufferSize::Size16).unwrap();
 
     let ts = log_common::get_time_now();
-    let d = log_common::LogData::new(log_common::Level::Info, ts, format!("test line 1"));
-    assert!(sender.send(d).is_ok());
+    let d = log_common::LogData::new(log_common::Level::Info, ts, "test line 1".to_string());
+    assert!(sender.send(d.into()).is_ok());
 
     let ts = log_common::get_time_now();
-    let d = log_common::LogData::new(log_common::Level::Info, ts, format!("test line 2"));
-    assert!(sender.send(d).is_ok());
+    let d = log_common::LogData::new(log_common::Level::Info, ts, "test line 2".to_string());
+    assert!(sender.send(d.into()).is_ok());
 
     let ts = log_common::get_time_now();
-    let d = log_common::LogData::new(log_common::Level::Info, ts, format!("test line 3"));
-    assert!(sender.send(d).is_ok());
+    let d = log_common::LogData::new(log_common::Level::Info, ts, "test line 3".to_string());
+    assert!(sender.send(d.into()).is_ok());
 
     let ts = log_common::get_time_now();
-    let d = log_common::LogData::new(log_common::Level::Error, ts, format!("test line 4"));
-    assert!(sender.send(d).is_ok());
+    let d = log_common::LogData::new(log_common::Level::Error, ts, "test line 4".to_string());
+    assert!(sender.send(d.into()).is_ok());
 
     std::thread::sleep(std::time::Duration::from_millis(500));
-    let ts = log_common::get_time_now();
-    let d = log_common::LogData::new(log_common::Level::Off, ts, format!("Off line"));
-    assert!(sender.send(d).is_ok());
+    assert!(sender.send(log_channel::Message::Shutdown).is_ok());
 
     for _k in 0..100 {
         std::thread::sleep(std::time::Duration::from_millis(100));
-        let ts = log_common::get_time_now();
-        let d = log_common::LogData::new(log_common::Level::Off, ts, format!("Off line"));
-        if sender.send(d).is_err() {
+        if sender.send(log_channel::Message::Shutdown).is_err() {
             break;
         }
     }
@@ -136,4 +147,623 @@ fn test_write_error() {
     assert!( lines[2].find("test line 2").is_some() );
     assert!( lines[3].find("test line 3").is_some() );
     assert!( lines[4].find("test line 4").is_some() );
-}
\ No newline at end of file
+}
+
+
+#[test]
+fn test_multiple_sinks() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(rw_dir).join("test_multiple_sinks.txt");
+
+    let (sender, receiver) = std::sync::mpsc::channel::<log_channel::Message>();
+
+    let (mut all_file, all_writer) = mock_writer::get_mock_text_data_writer();
+    let (mut warn_file, warn_writer) = mock_writer::get_mock_text_data_writer();
+    let sinks = vec!(
+        Sink::new("all", fp.clone(), Box::new(all_writer)),
+        Sink::new("warn", fp, Box::new(warn_writer))
+            .with_min_level(log_common::Level::Warn)
+            .with_formatter(Box::new(|d| format!("W {}", d.line()))),
+    );
+    spawn_mocked_sinks(log_common::Level::Error, receiver, sinks, BufferSize::Size16);
+
+    let ts = log_common::get_time_now();
+    assert!(sender.send(log_common::LogData::new(log_common::Level::Info, ts, "test line 1".to_string()).into()).is_ok());
+    assert!(sender.send(log_common::LogData::new(log_common::Level::Warn, ts, "test line 2".to_string()).into()).is_ok());
+    assert!(sender.send(log_common::LogData::new(log_common::Level::Error, ts, "test line 3".to_string()).into()).is_ok());
+    shutdown_and_wait(&sender);
+
+    all_file.process_queued_messages();
+    let lines = all_file.get_mock_data();
+    assert!(4 == lines.len()); // lines plus header
+    assert!( lines[1].contains("test line 1") );
+    assert!( lines[3].contains("test line 3") );
+
+    warn_file.process_queued_messages();
+    let lines = warn_file.get_mock_data();
+    assert!(3 == lines.len()); // lines plus header
+    assert!("W test line 2" == lines[1]);
+    assert!("W test line 3" == lines[2]);
+}
+
+
+#[test]
+fn test_failing_sink() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(rw_dir).join("test_failing_sink.txt");
+
+    let (sender, receiver) = std::sync::mpsc::channel::<log_channel::Message>();
+
+    let (mut good_file, good_writer) = mock_writer::get_mock_text_data_writer();
+    let sinks = vec!(
+        Sink::new("broken", fp.clone(), Box::new(mock_writer::FailingDataWriter)),
+        Sink::new("good", fp, Box::new(good_writer)),
+    );
+    spawn_mocked_sinks(log_common::Level::Error, receiver, sinks, BufferSize::Size16);
+
+    let ts = log_common::get_time_now();
+    assert!(sender.send(log_common::LogData::new(log_common::Level::Error, ts, "test line 1".to_string()).into()).is_ok());
+    assert!(sender.send(log_common::LogData::new(log_common::Level::Error, ts, "test line 2".to_string()).into()).is_ok());
+    shutdown_and_wait(&sender);
+
+    good_file.process_queued_messages();
+    let lines = good_file.get_mock_data();
+
+    // Both dumps reach the good sink, the second one reporting the broken sink
+    assert!(5 == lines.len());
+    assert!( lines[1].contains("test line 1") );
+    assert!( lines[3].contains("Sink 'broken' failed") );
+    assert!( lines[4].contains("test line 2") );
+}
+
+
+#[test]
+fn test_streaming_sink() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(rw_dir).join("test_streaming_sink.txt");
+
+    let (sender, receiver) = std::sync::mpsc::channel::<log_channel::Message>();
+
+    let (mut dump_file, dump_writer) = mock_writer::get_mock_text_data_writer();
+    let (mut stream_file, stream_writer) = mock_writer::get_mock_text_data_writer();
+    let sinks = vec!(
+        Sink::new("dump", fp.clone(), Box::new(dump_writer)),
+        Sink::new("stream", fp, Box::new(stream_writer))
+            .with_min_level(log_common::Level::Info)
+            .streaming(),
+    );
+    spawn_mocked_sinks(log_common::Level::Error, receiver, sinks, BufferSize::Size16);
+
+    let ts = log_common::get_time_now();
+    assert!(sender.send(log_common::LogData::new(log_common::Level::Trace, ts, "test line 1".to_string()).into()).is_ok());
+    assert!(sender.send(log_common::LogData::new(log_common::Level::Info, ts, "test line 2".to_string()).into()).is_ok());
+    std::thread::sleep(std::time::Duration::from_millis(200));
+
+    // Written through without waiting for a dump, Trace is below stream level
+    stream_file.process_queued_messages();
+    assert!(1 == stream_file.get_mock_data().len());
+    assert!( stream_file.get_mock_data()[0].contains("test line 2") );
+    dump_file.process_queued_messages();
+    assert!(dump_file.get_mock_data().is_empty());
+
+    assert!(sender.send(log_common::LogData::new(log_common::Level::Error, ts, "test line 3".to_string()).into()).is_ok());
+    shutdown_and_wait(&sender);
+
+    stream_file.process_queued_messages();
+    let lines = stream_file.get_mock_data();
+    assert!(2 == lines.len()); // No header for streams
+    assert!( lines[1].contains("test line 3") );
+
+    dump_file.process_queued_messages();
+    let lines = dump_file.get_mock_data();
+    assert!(4 == lines.len()); // lines plus header
+    assert!( lines[1].contains("test line 1") );
+    assert!( lines[3].contains("test line 3") );
+}
+
+
+
+#[test]
+fn test_failing_streaming_sink() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(rw_dir).join("test_failing_streaming_sink.txt");
+
+    let (mut mock_file, mock_writer) = mock_writer::get_mock_text_data_writer();
+    let log = crate::LoggerBuilder::new(log_common::Level::Info, log_common::Level::Error, BufferSize::Size16)
+        .add_sink(Sink::new("mock", fp.clone(), Box::new(mock_writer)))
+        .add_sink(Sink::new("broken", fp, Box::new(mock_writer::FailingDataWriter)).streaming())
+        .build()
+        .unwrap();
+
+    log.info_str("test line 1");
+    log.info_str("test line 2");
+
+    // Stream failures are counted apart from dump failures
+    let stats = log.stats();
+    assert!(2 == stats.stream_failures);
+    assert!(0 == stats.dump_failures);
+    assert!(Some("Sink 'broken' failed: Stream open failed") == stats.last_error.as_deref());
+
+    log.error_str("test line 3");
+    log.shutdown();
+
+    // Failures are reported in the buffered sink
+    mock_file.process_queued_messages();
+    let lines = mock_file.get_mock_data();
+    assert!(7 == lines.len()); // header, 3 lines and 3 failures
+    assert!(lines[2].contains("Sink 'broken' failed"));
+}
+
+
+#[test]
+fn test_dropped_reported() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(rw_dir).join("test_dropped_reported.txt");
+
+    let (sender, receiver) = log_channel::bounded(4, log_channel::OverflowPolicy::DropOldestBelow(log_common::Level::Warn));
+
+    // Fill channel before receiver is running
+    for k in 0..10 {
+        let d = log_common::LogData::new(log_common::Level::Info, log_common::get_time_now(), format!("test line {}", k));
+        assert!(sender.send(d.into()).is_ok());
+    }
+    let d = log_common::LogData::new(log_common::Level::Error, log_common::get_time_now(), "test error".to_string());
+    assert!(sender.send(d.into()).is_ok());
+
+    let (mut mock_file, mock_writer) = mock_writer::get_mock_text_data_writer();
+    let sinks = vec!(Sink::new("mock", fp, Box::new(mock_writer)));
+    spawn_mocked_sinks(log_common::Level::Error, receiver, sinks, BufferSize::Size16);
+
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    drop(sender);
+    std::thread::sleep(std::time::Duration::from_millis(200));
+
+    mock_file.process_queued_messages();
+    let lines = mock_file.get_mock_data();
+    assert!( lines[1].contains("test line 7") );
+    assert!( lines[3].contains("test line 9") );
+    assert!( lines[4].contains("test error") );
+    assert!( lines[5].contains("7 messages were dropped") );
+}
+
+
+#[test]
+fn test_per_thread_rings() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(rw_dir).join("test_per_thread_rings.txt");
+
+    let (mut mock_file, mock_writer) = mock_writer::get_mock_text_data_writer();
+    let log = crate::LoggerBuilder::new(log_common::Level::Trace, log_common::Level::Error, BufferSize::Size16)
+        .add_sink(Sink::new("mock", fp, Box::new(mock_writer)))
+        .per_thread_rings(BufferSize::Size64)
+        .build()
+        .unwrap();
+
+    let thread_log = log.clone();
+    std::thread::spawn(move || {
+        thread_log.info_str("test line 1");
+        thread_log.info_str("test line 2");
+    }).join().unwrap();
+    log.info_str("test line 3");
+    log.error_str("test line 4");
+    log.shutdown();
+
+    mock_file.process_queued_messages();
+    let lines = mock_file.get_mock_data();
+    assert!(5 == lines.len()); // lines plus header
+    assert!( lines[1].contains("test line 1") );
+    assert!( lines[2].contains("test line 2") );
+    assert!( lines[3].contains("test line 3") );
+    assert!( lines[4].contains("test line 4") );
+}
+
+
+#[test]
+fn test_runtime_dump_control() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(rw_dir).join("test_runtime_dump_control.txt");
+
+    let (mut mock_file, mock_writer) = mock_writer::get_mock_text_data_writer();
+    let log = crate::LoggerBuilder::new(log_common::Level::Trace, log_common::Level::Error, BufferSize::Size16)
+        .add_sink(Sink::new("mock", fp, Box::new(mock_writer)))
+        .build()
+        .unwrap();
+
+    // Warn does not dump until the dump level is raised
+    log.warn_str("test line 1");
+    log.set_dump_level(log_common::Level::Warn);
+    log.warn_str("test line 2");
+
+    // Paused dumps keep buffering
+    log.pause_dumps();
+    log.info_str("test line 3");
+    log.error_str("test line 4");
+    log.resume_dumps();
+    assert!(Some(log_common::Level::Warn) == log.dump_level());
+    log.warn_str("test line 5");
+    log.shutdown();
+
+    mock_file.process_queued_messages();
+    let lines = mock_file.get_mock_data();
+    assert!(7 == lines.len()); // lines plus 2 headers
+    assert!("\n" == lines[0]);
+    assert!( lines[1].contains("test line 1") );
+    assert!( lines[2].contains("test line 2") );
+    assert!("\n" == lines[3]);
+    assert!( lines[4].contains("test line 3") );
+    assert!( lines[5].contains("test line 4") );
+    assert!( lines[6].contains("test line 5") );
+}
+
+
+#[test]
+fn test_trigger_policy() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(rw_dir).join("test_trigger_policy.txt");
+
+    let (mut mock_file, mock_writer) = mock_writer::get_mock_text_data_writer();
+    let log = crate::LoggerBuilder::new(log_common::Level::Trace, log_common::Level::Error, BufferSize::Size16)
+        .add_sink(Sink::new("mock", fp, Box::new(mock_writer)))
+        .trigger_policy(crate::TriggerPolicy::Cooldown(std::time::Duration::from_secs(60)))
+        .build()
+        .unwrap();
+
+    // Only the first error dumps, the storm after it is buffered
+    log.error_str("test line 1");
+    log.error_str("test line 2");
+    log.error_str("test line 3");
+
+    log.set_trigger_policy(crate::TriggerPolicy::Immediate);
+    log.error_str("test line 4");
+    log.shutdown();
+
+    mock_file.process_queued_messages();
+    let lines = mock_file.get_mock_data();
+    assert!(6 == lines.len()); // lines plus 2 headers
+    assert!( lines[1].contains("test line 1") );
+    assert!("\n" == lines[2]);
+    assert!( lines[3].contains("test line 2") );
+    assert!( lines[5].contains("test line 4") );
+}
+
+
+#[test]
+fn test_off_record_does_not_stop() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(rw_dir).join("test_off_record_does_not_stop.txt");
+
+    let (sender, receiver) = std::sync::mpsc::channel::<log_channel::Message>();
+    let mut mock_file = spawn_mocked(log_common::Level::Error, receiver, fp, BufferSize::Size16).unwrap();
+
+    // Only a Shutdown command ends the thread, an Off record is ignored
+    let ts = log_common::get_time_now();
+    assert!(sender.send(log_common::LogData::new(log_common::Level::Off, ts, "test line 1".to_string()).into()).is_ok());
+    assert!(sender.send(log_common::LogData::new(log_common::Level::Error, ts, "test line 2".to_string()).into()).is_ok());
+
+    let (ack, acked) = std::sync::mpsc::channel();
+    assert!(sender.send(log_channel::Message::Flush(ack)).is_ok());
+    assert!(acked.recv_timeout(std::time::Duration::from_secs(5)).is_ok());
+
+    let (reply, answer) = std::sync::mpsc::channel();
+    assert!(sender.send(log_channel::Message::Query(log_channel::Query::DumpLevel(reply))).is_ok());
+    assert!(Ok(log_common::Level::Error) == answer.recv_timeout(std::time::Duration::from_secs(5)));
+    shutdown_and_wait(&sender);
+
+    mock_file.process_queued_messages();
+    let lines = mock_file.get_mock_data();
+    assert!(2 == lines.len()); // line plus header
+    assert!( lines[1].contains("test line 2") );
+}
+
+
+#[test]
+fn test_dump_now_and_flush() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(rw_dir).join("test_dump_now_and_flush.txt");
+
+    let (mut mock_file, mock_writer) = mock_writer::get_mock_text_data_writer();
+    let log = crate::LoggerBuilder::new(log_common::Level::Trace, log_common::Level::Error, BufferSize::Size16)
+        .add_sink(Sink::new("mock", fp, Box::new(mock_writer)))
+        .build()
+        .unwrap();
+
+    log.info_str("test line 1");
+    log.pause_dumps();
+    log.dump_now("health check failed");
+    assert!(log.flush().is_ok());
+
+    // Dump completed before flush returned
+    mock_file.process_queued_messages();
+    let lines = mock_file.get_mock_data();
+    assert!(2 == lines.len()); // header plus line
+    assert!("\nDump requested: health check failed\n" == lines[0]);
+    assert!( lines[1].contains("test line 1") );
+
+    log.shutdown();
+    assert!(log.flush().is_err());
+}
+
+
+#[test]
+fn test_snapshot() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(rw_dir).join("test_snapshot.txt");
+
+    let (mut mock_file, mock_writer) = mock_writer::get_mock_text_data_writer();
+    let (_stream_file, stream_writer) = mock_writer::get_mock_text_data_writer();
+    let log = crate::LoggerBuilder::new(log_common::Level::Trace, log_common::Level::Error, BufferSize::Size16)
+        .add_sink(Sink::new("mock", fp.clone(), Box::new(mock_writer)))
+        .add_sink(Sink::new("stream", fp, Box::new(stream_writer)).streaming())
+        .build()
+        .unwrap();
+
+    log.info_str("test line 1");
+    log.warn_str("test line 2");
+
+    let snapshot = log.snapshot().unwrap();
+    assert!(1 == snapshot.len()); // Streaming sink has no buffer
+    assert!("mock" == snapshot[0].name);
+    assert!(2 == snapshot[0].lines.len());
+    assert!( snapshot[0].lines[0].contains("test line 1") );
+    assert!( snapshot[0].lines[1].contains("test line 2") );
+
+    // Nothing written and buffer kept
+    mock_file.process_queued_messages();
+    assert!(mock_file.get_mock_data().is_empty());
+    assert!(snapshot == log.snapshot().unwrap());
+
+    log.shutdown();
+    assert!(log.snapshot().is_none());
+}
+
+
+#[test]
+fn test_stats() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(rw_dir).join("test_stats.txt");
+
+    let (mut mock_file, mock_writer) = mock_writer::get_mock_text_data_writer();
+    let log = crate::LoggerBuilder::new(log_common::Level::Info, log_common::Level::Error, BufferSize::Size16)
+        .add_sink(Sink::new("mock", fp.clone(), Box::new(mock_writer)))
+        .add_sink(Sink::new("broken", fp, Box::new(mock_writer::FailingDataWriter)))
+        .stats_in_dump_header()
+        .build()
+        .unwrap();
+
+    log.trace_str("test line 1"); // Filtered
+    for k in 2..22 {
+        log.info_str(&format!("test line {}", k));
+    }
+    log.error_str("test line 22");
+
+    let stats = log.stats();
+    assert!(21 == stats.sent);
+    assert!(1 == stats.filtered);
+    assert!(21 == stats.received);
+    assert!(1 == stats.dumps);
+    assert!(1 == stats.dump_failures);
+    assert!(0 == stats.dropped);
+    assert!(stats.last_error.as_deref().unwrap().contains("Sink 'broken' failed"));
+
+    // Both sinks buffered 21 lines in a ring of 16
+    assert!(10 == stats.evicted);
+
+    mock_file.process_queued_messages();
+    let lines = mock_file.get_mock_data();
+    assert!(17 == lines.len()); // header plus lines
+    assert!(lines[0].starts_with("\nStats: sent=21 filtered=1 dropped=0 received=21 evicted=10 undelivered=0 dumps=1 dump_failures=0"));
+    assert!(lines[1].contains("test line 7"));
+    assert!(lines[16].contains("test line 22"));
+    let written: usize = lines.iter().map(|l| l.len()).sum();
+    assert!(written as u64 == stats.bytes_written);
+
+    log.shutdown();
+    let stats = log.stats();
+    assert!(21 == stats.sent);
+    assert!(0 == stats.received);
+    assert!(Some("Receiver is not running") == stats.last_error.as_deref());
+}
+
+
+#[test]
+fn test_restart_after_panic() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(rw_dir).join("test_restart_after_panic.txt");
+
+    let (mut mock_file, mock_writer) = mock_writer::get_mock_text_data_writer();
+    let sink = Sink::new("mock", fp, Box::new(mock_writer))
+        .with_formatter(Box::new(|d| {
+            if "boom" == d.line() {
+                panic!("formatter bug");
+            }
+            d.as_string()
+        }));
+    let log = crate::LoggerBuilder::new(log_common::Level::Trace, log_common::Level::Error, BufferSize::Size16)
+        .add_sink(sink)
+        .build()
+        .unwrap();
+
+    log.info_str("test line 1");
+    log.info_str("boom");
+
+    // Same sender keeps working after the restart
+    log.info_str("test line 2");
+    log.error_str("test line 3");
+    assert!(log.flush().is_ok());
+    assert!(1 == log.stats().restarts);
+    log.shutdown();
+
+    mock_file.process_queued_messages();
+    let lines = mock_file.get_mock_data();
+    assert!(5 == lines.len()); // 2 headers plus lines, buffer before the panic is discarded
+    assert!("\n" == lines[0]);
+    assert!( lines[1].contains("Receiver restarted after panic: formatter bug") );
+    assert!("\n" == lines[2]);
+    assert!( lines[3].contains("test line 2") );
+    assert!( lines[4].contains("test line 3") );
+}
+
+
+#[test]
+fn test_restart_after_writer_panic() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(rw_dir).join("test_restart_after_writer_panic.txt");
+
+    let (mut mock_file, mock_writer) = mock_writer::get_mock_text_data_writer();
+    let log = crate::LoggerBuilder::new(log_common::Level::Trace, log_common::Level::Error, BufferSize::Size16)
+        .add_sink(Sink::new("mock", fp.clone(), Box::new(mock_writer)))
+        .add_sink(Sink::new("buggy", fp, Box::new(mock_writer::PanickingDataWriter)))
+        .build()
+        .unwrap();
+
+    // Writer panics on the dump, then again on the dump reporting the restart, so its sink is removed
+    log.error_str("test line 1");
+    assert!(log.try_log(log_common::Level::Info, "test line 2").is_ok());
+    assert!(log.flush().is_ok());
+    let stats = log.stats();
+    assert!(2 == stats.restarts);
+    assert!(stats.last_error.as_deref().unwrap().contains("sink 'buggy' removed after panicking twice"));
+
+    log.error_str("test line 3");
+    log.shutdown();
+
+    mock_file.process_queued_messages();
+    let lines = mock_file.get_mock_data();
+    assert!(lines.iter().any(|l| l.contains("Receiver restarted after panic: writer bug, sink 'buggy' removed")));
+    assert!(lines.last().unwrap().contains("test line 3"));
+}
+
+
+#[test]
+fn test_restart_after_panic_dumps_off() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(rw_dir).join("test_restart_after_panic_dumps_off.txt");
+
+    let (_mock_file, mock_writer) = mock_writer::get_mock_text_data_writer();
+    let sink = Sink::new("mock", fp, Box::new(mock_writer))
+        .with_formatter(Box::new(|d| {
+            if "boom" == d.line() {
+                panic!("formatter bug");
+            }
+            d.as_string()
+        }));
+    let log = crate::LoggerBuilder::new(log_common::Level::Trace, log_common::Level::Error, BufferSize::Size16)
+        .add_sink(sink)
+        .build()
+        .unwrap();
+
+    // Restarted receiver runs with dumps off instead of panicking again
+    log.set_dump_level(log_common::Level::Off);
+    log.info_str("boom");
+    log.error_str("test line 1");
+    assert!(log.flush().is_ok());
+    let stats = log.stats();
+    assert!(1 == stats.restarts);
+    assert!(Some(log_common::Level::Off) == log.dump_level());
+    log.shutdown();
+}
+
+
+#[test]
+fn test_crash_recovery() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(&rw_dir).join("test_crash_recovery.txt");
+    let crash_fp = std::path::PathBuf::from(&rw_dir).join("test_crash_recovery.ring");
+    let _ret = std::fs::remove_file(&crash_fp);
+
+    // Previous run killed before it could dump
+    let (mut ring, _recovered) = MmapRing::open(&crash_fp, 16, 1024).unwrap();
+    ring.push("test line 1\n");
+    ring.push("test line 2\n");
+    std::mem::forget(ring);
+
+    let (mut mock_file, mock_writer) = mock_writer::get_mock_text_data_writer();
+    let log = crate::LoggerBuilder::new(log_common::Level::Trace, log_common::Level::Error, BufferSize::Size16)
+        .add_sink(Sink::new("mock", fp, Box::new(mock_writer)).crash_safe(crash_fp.to_str().unwrap()))
+        .build()
+        .unwrap();
+
+    log.info_str("test line 3");
+    assert!(log.flush().is_ok());
+    mock_file.process_queued_messages();
+    let lines = mock_file.get_mock_data();
+    assert!(3 == lines.len()); // header plus lines
+    assert!("\nRecovered from previous crash\n" == lines[0]);
+    assert!("test line 1\n" == lines[1]);
+    assert!("test line 2\n" == lines[2]);
+
+    // This run's buffer is in the crash file until shutdown
+    let bytes = std::fs::read(&crash_fp).unwrap();
+    assert!(String::from_utf8_lossy(&bytes).contains("test line 3"));
+
+    log.shutdown();
+    let (_ring, recovered) = MmapRing::open(&crash_fp, 16, 1024).unwrap();
+    assert!(recovered.is_empty());
+}
+
+
+#[test]
+fn test_previous_run_reported() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(&rw_dir).join("test_previous_run_reported.txt");
+    let marker_fp = std::path::PathBuf::from(&rw_dir).join("test_previous_run_reported.running");
+
+    // Marker left by a run that crashed
+    std::fs::write(&marker_fp, "pid=4242\nstarted=2024-01-02T03:04:05+00:00\n").unwrap();
+
+    let (mut mock_file, mock_writer) = mock_writer::get_mock_text_data_writer();
+    let log = crate::LoggerBuilder::new(log_common::Level::Trace, log_common::Level::Error, BufferSize::Size16)
+        .add_sink(Sink::new("mock", fp, Box::new(mock_writer)))
+        .run_marker(marker_fp.to_str().unwrap())
+        .build()
+        .unwrap();
+    assert!(log.flush().is_ok());
+
+    mock_file.process_queued_messages();
+    let lines = mock_file.get_mock_data();
+    assert!(2 == lines.len()); // header plus line
+    assert!( lines[1].contains("Previous run terminated uncleanly: pid 4242, started 2024-01-02T03:04:05+00:00") );
+
+    // Marker of this run is kept until a clean shutdown
+    let marker = std::fs::read_to_string(&marker_fp).unwrap();
+    assert!(marker.contains(&format!("pid={}", std::process::id())));
+    log.shutdown();
+    assert!(!marker_fp.exists());
+}
+
+
+#[test]
+fn test_run_marker_not_left_on_failed_start() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(&rw_dir).join("test_run_marker_not_left_on_failed_start.txt");
+    let crash_fp = std::path::PathBuf::from(&rw_dir).join("no_such_directory").join("crash.ring");
+    let marker_fp = std::path::PathBuf::from(&rw_dir).join("test_run_marker_not_left_on_failed_start.running");
+    let _r = std::fs::remove_file(&marker_fp);
+
+    // Crash file can not be opened, so the sink fails to activate
+    let (_mock_file, mock_writer) = mock_writer::get_mock_text_data_writer();
+    let log = crate::LoggerBuilder::new(log_common::Level::Trace, log_common::Level::Error, BufferSize::Size16)
+        .add_sink(Sink::new("mock", fp, Box::new(mock_writer)).crash_safe(crash_fp.to_str().unwrap()))
+        .run_marker(marker_fp.to_str().unwrap())
+        .build();
+    assert!(log.is_err());
+    assert!(!marker_fp.exists());
+}
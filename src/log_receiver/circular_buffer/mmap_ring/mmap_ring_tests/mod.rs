@@ -0,0 +1,99 @@
+#![cfg(test)]
+
+use super::*;
+
+/// Path in the test directory, removing any file left by a previous test run
+fn ring_path(name: &str) -> std::path::PathBuf {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(rw_dir).join(name);
+    let _ret = std::fs::remove_file(&fp);
+    fp
+}
+
+
+#[test]
+fn test_recover_after_crash() {
+    let fp = ring_path("test_mmap_recover_after_crash.ring");
+
+    let (mut ring, recovered) = MmapRing::open(&fp, 4, 64).unwrap();
+    assert!(recovered.is_empty());
+    ring.push("line 1\n");
+    ring.push("line 2\n");
+    std::mem::forget(ring); // Crash, no clean close
+
+    let (_ring, recovered) = MmapRing::open(&fp, 4, 64).unwrap();
+    assert!(2 == recovered.len());
+    assert!("line 1\n" == recovered[0]);
+    assert!("line 2\n" == recovered[1]);
+}
+
+
+#[test]
+fn test_clean_close() {
+    let fp = ring_path("test_mmap_clean_close.ring");
+
+    let (mut ring, _recovered) = MmapRing::open(&fp, 4, 64).unwrap();
+    ring.push("line 1\n");
+    drop(ring);
+
+    let (ring, recovered) = MmapRing::open(&fp, 4, 64).unwrap();
+    assert!(recovered.is_empty());
+
+    // Reopening resets the file, so a crash straight after recovers nothing old
+    std::mem::forget(ring);
+    let (_ring, recovered) = MmapRing::open(&fp, 4, 64).unwrap();
+    assert!(recovered.is_empty());
+}
+
+
+#[test]
+fn test_wrap_and_dumped() {
+    let fp = ring_path("test_mmap_wrap_and_dumped.ring");
+
+    let (mut ring, _recovered) = MmapRing::open(&fp, 4, 64).unwrap();
+    for k in 0..10 {
+        ring.push(&format!("line {}", k));
+    }
+    std::mem::forget(ring);
+
+    // Only the newest lines that fit are kept
+    let (mut ring, recovered) = MmapRing::open(&fp, 4, 64).unwrap();
+    assert!(["line 6", "line 7", "line 8", "line 9"] == recovered[..]);
+
+    // Dumped lines are not recovered
+    ring.push("line 10");
+    ring.push("line 11");
+    ring.mark_dumped();
+    ring.push("line 12");
+    std::mem::forget(ring);
+
+    let (_ring, recovered) = MmapRing::open(&fp, 4, 64).unwrap();
+    assert!(["line 12"] == recovered[..]);
+}
+
+
+#[test]
+fn test_truncate() {
+    let fp = ring_path("test_mmap_truncate.ring");
+
+    let (mut ring, _recovered) = MmapRing::open(&fp, 4, 24).unwrap();
+    ring.push("0123456789");
+    ring.push("ab\u{e9}\u{e9}\u{e9}\u{e9}"); // 2 byte characters must not be split
+    std::mem::forget(ring);
+
+    // Different geometry on the next run still recovers
+    let (_ring, recovered) = MmapRing::open(&fp, 16, 64).unwrap();
+    assert!("01234567" == recovered[0]);
+    assert!("ab\u{e9}\u{e9}\u{e9}" == recovered[1]);
+}
+
+
+#[test]
+fn test_not_a_ring() {
+    let fp = ring_path("test_mmap_not_a_ring.ring");
+    std::fs::write(&fp, "some other file").unwrap();
+
+    let (_ring, recovered) = MmapRing::open(&fp, 4, 64).unwrap();
+    assert!(recovered.is_empty());
+}
@@ -0,0 +1,180 @@
+// mmap ring module
+//
+// Copy of a sink's buffer kept in a memory mapped file.
+// Writes to the mapping reach the file even if the process is killed,
+// so the lines of a run that never dumped can be recovered on the next start.
+//
+// File layout, little endian:
+// Header, `HEADER_SIZE` bytes
+//   [0..8]   magic
+//   [8..12]  slot count
+//   [12..16] slot size in bytes
+//   [16..20] 1 while running, 0 after a clean close
+//   [24..32] sequence number of the next line
+//   [32..40] sequence number of the last line dumped
+// Followed by `slot count` slots of `slot size` bytes
+//   [0..8]   sequence number, 0 if never written
+//   [8..12]  length of line
+//   [16..]   line, truncated to fit
+
+#[cfg(test)]
+mod mmap_ring_tests;
+
+const MAGIC: &[u8; 8] = b"PLRING01";
+const HEADER_SIZE: usize = 64;
+const SLOT_HEADER_SIZE: usize = 16;
+
+const IS_DIRTY_OFFSET: usize = 16;
+const NEXT_SEQ_OFFSET: usize = 24;
+const DUMPED_SEQ_OFFSET: usize = 32;
+
+/// Ring of lines stored in a memory mapped file
+pub struct MmapRing {
+    map: memmap2::MmapMut,
+    slot_count: usize,
+    slot_size: usize,
+    next_seq: u64,
+}
+
+impl MmapRing {
+
+    /// Open or create ring file at `path`
+    ///
+    /// If the file is from a run that did not close cleanly,
+    /// the lines not dumped by that run are returned, oldest first.
+    /// The file is then reset for this run.
+    pub fn open(
+        path: &std::path::Path,
+        slot_count: usize,
+        slot_size: usize,
+    ) -> Result<(Self, std::vec::Vec<String>), &'static str> {
+        debug_assert!(0 < slot_count);
+        debug_assert!(SLOT_HEADER_SIZE < slot_size);
+
+        let recovered = match std::fs::read(path) {
+            Ok(bytes) => recover(&bytes),
+            Err(_) => vec!(), // No previous run
+        };
+
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .map_err(|_| "Failed to open crash buffer file")?;
+        file.set_len((HEADER_SIZE + slot_count * slot_size) as u64)
+            .map_err(|_| "Failed to size crash buffer file")?;
+
+        // Safety: the file is owned by this ring, other processes must not modify it while mapped
+        let mut map = unsafe { memmap2::MmapMut::map_mut(&file) }
+            .map_err(|_| "Failed to map crash buffer file")?;
+
+        map[0..8].copy_from_slice(MAGIC);
+        write_u32(&mut map, 8, slot_count as u32);
+        write_u32(&mut map, 12, slot_size as u32);
+        write_u64(&mut map, NEXT_SEQ_OFFSET, 1);
+        write_u64(&mut map, DUMPED_SEQ_OFFSET, 0);
+        write_u32(&mut map, IS_DIRTY_OFFSET, 1);
+
+        let ring = Self {
+            map,
+            slot_count,
+            slot_size,
+            next_seq: 1,
+        };
+        Ok((ring, recovered))
+    }
+
+    /// Store line, overwriting the oldest once full
+    ///
+    /// Lines longer than the slot are truncated.
+    pub fn push(&mut self, line: &str) {
+        let seq = self.next_seq;
+        let offset = HEADER_SIZE + (seq as usize % self.slot_count) * self.slot_size;
+        let data = truncate(line, self.slot_size - SLOT_HEADER_SIZE).as_bytes();
+
+        // Sequence number is written last, so a line torn by a crash is never recovered
+        write_u64(&mut self.map, offset, 0);
+        std::sync::atomic::fence(std::sync::atomic::Ordering::Release);
+        let start = offset + SLOT_HEADER_SIZE;
+        self.map[start..start + data.len()].copy_from_slice(data);
+        write_u32(&mut self.map, offset + 8, data.len() as u32);
+        std::sync::atomic::fence(std::sync::atomic::Ordering::Release);
+        write_u64(&mut self.map, offset, seq);
+
+        self.next_seq += 1;
+        write_u64(&mut self.map, NEXT_SEQ_OFFSET, self.next_seq);
+    }
+
+    /// Lines stored so far have been dumped and will not be recovered
+    pub fn mark_dumped(&mut self) {
+        write_u64(&mut self.map, DUMPED_SEQ_OFFSET, self.next_seq - 1);
+    }
+}
+
+impl Drop for MmapRing {
+    /// Clean close, nothing will be recovered from this run
+    fn drop(&mut self) {
+        write_u32(&mut self.map, IS_DIRTY_OFFSET, 0);
+        let _ret = self.map.flush();
+    }
+}
+
+
+/// Lines not dumped by a run that did not close cleanly, oldest first
+///
+/// Anything that does not look like a ring file gives nothing.
+fn recover(bytes: &[u8]) -> std::vec::Vec<String> {
+    if bytes.len() < HEADER_SIZE || &bytes[0..8] != MAGIC || 0 == read_u32(bytes, IS_DIRTY_OFFSET) {
+        return vec!();
+    }
+    let slot_count = read_u32(bytes, 8) as usize;
+    let slot_size = read_u32(bytes, 12) as usize;
+    let dumped_seq = read_u64(bytes, DUMPED_SEQ_OFFSET);
+    if slot_size <= SLOT_HEADER_SIZE || bytes.len() < HEADER_SIZE + slot_count * slot_size {
+        return vec!();
+    }
+
+    let mut lines = std::vec::Vec::new();
+    for k in 0..slot_count {
+        let offset = HEADER_SIZE + k * slot_size;
+        let seq = read_u64(bytes, offset);
+        let len = read_u32(bytes, offset + 8) as usize;
+        if seq > dumped_seq && len <= slot_size - SLOT_HEADER_SIZE {
+            let start = offset + SLOT_HEADER_SIZE;
+            lines.push((seq, String::from_utf8_lossy(&bytes[start..start + len]).into_owned()));
+        }
+    }
+    lines.sort_by_key(|(seq, _)| *seq);
+    lines.into_iter().map(|(_, line)| line).collect()
+}
+
+/// Longest prefix of `line` of at most `max` bytes, not splitting a character
+fn truncate(line: &str, max: usize) -> &str {
+    if line.len() <= max {
+        line
+    } else {
+        let mut end = max;
+        while !line.is_char_boundary(end) {
+            end -= 1;
+        }
+        &line[..end]
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+fn write_u32(bytes: &mut [u8], offset: usize, value: u32) {
+    bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn write_u64(bytes: &mut [u8], offset: usize, value: u64) {
+    bytes[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
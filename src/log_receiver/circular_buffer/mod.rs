@@ -1,18 +1,27 @@
 pub mod data_writer;
+pub mod mmap_ring;
 
 #[cfg(test)]
-mod circular_buffer_tests;
+pub(crate) mod circular_buffer_tests;
 #[cfg(test)]
 mod log_receiver_tests;
 
 pub use data_writer::TextDataWriter;
 
+/// Written before each dump, to insert new line between data dumps
+pub const DUMP_HEADER: &str = "\n";
+
 pub struct CircularStringsBuffer<T: TextDataWriter + Send > {
     buffer: std::vec::Vec<String>,
     max_size: usize,
     internal_index: usize,
     external_index: usize,
     writer: T,
+    /// Lines overwritten before being written
+    evicted: u64,
+    bytes_written: u64,
+    /// Copy of the buffer that survives a crash
+    mirror: Option<mmap_ring::MmapRing>,
 }
 
 impl<T: TextDataWriter + Send > CircularStringsBuffer<T>{
@@ -27,15 +36,28 @@ impl<T: TextDataWriter + Send > CircularStringsBuffer<T>{
             internal_index: 0,
             external_index: 0,
             writer,
+            evicted: 0,
+            bytes_written: 0,
+            mirror: None,
         }
     }
 
+    /// Keep a copy of every line pushed in `mirror`, cleared whenever this buffer is
+    pub fn with_mirror(mut self, mirror: mmap_ring::MmapRing) -> Self {
+        self.mirror = Some(mirror);
+        self
+    }
+
     fn reset_fast(&mut self) {
         self.internal_index = 0;
         self.external_index = 0;
+        if let Some(m) = self.mirror.as_mut() {
+            m.mark_dumped();
+        }
     }
 
-    fn reset(&mut self) {
+    /// Discard all buffered lines
+    pub fn reset(&mut self) {
         self.reset_fast();
         for k in self.buffer.iter_mut() {
             (*k).clear();
@@ -43,9 +65,15 @@ impl<T: TextDataWriter + Send > CircularStringsBuffer<T>{
     }
 
     pub fn push(&mut self, new_value: String) {
+        if self.external_index >= self.max_size {
+            self.evicted += 1;
+        }
         if self.internal_index >= self.max_size {
             self.internal_index = 0;
         }
+        if let Some(m) = self.mirror.as_mut() {
+            m.push(&new_value);
+        }
         self.buffer[self.internal_index] = new_value;
         self.internal_index += 1;
         self.external_index += 1;
@@ -62,7 +90,11 @@ impl<T: TextDataWriter + Send > CircularStringsBuffer<T>{
     }
 
     pub fn write_to_file_and_clear(&mut self, f: &std::path::Path) -> Result<(), &'static str> {
-        const DUMP_HEADER: &str = "\n"; // To insert new line between data dumps
+        self.write_to_file_and_clear_with_header(f, DUMP_HEADER)
+    }
+
+    /// Same as `write_to_file_and_clear()`, with `header` written before the data
+    pub fn write_to_file_and_clear_with_header(&mut self, f: &std::path::Path, header: &str) -> Result<(), &'static str> {
         let qty = self.get_qty_in_buffer();
         let mut ans: Result<(), &'static str> = Ok(());
         if qty > 0 {
@@ -73,15 +105,17 @@ impl<T: TextDataWriter + Send > CircularStringsBuffer<T>{
 
             if success.is_err() {
                 ans = Err("File open failed in write_to_file_and_clear()");
-            } else if self.writer.write(DUMP_HEADER).is_err() {
+            } else if self.writer.write_header(header).is_err() {
                 ans = Err("write_to_file_and_clear() failed to write header");
             } else {
+                self.bytes_written += header.len() as u64;
                 for k in start_index..end_index {
                     if let Some(line) = self.get(k) {
                         if self.writer.write(&line).is_err() {
                             ans = Err("write_to_file_and_clear() failed to write data");
                             break;
                         }
+                        self.bytes_written += line.len() as u64;
                     } else {
                         debug_assert!(false);
                         self.reset_fast(); // May lose data, but will get buffer back to a working state if indexes are broken.
@@ -103,6 +137,28 @@ impl<T: TextDataWriter + Send > CircularStringsBuffer<T>{
         ans
     }
 
+    /// Number of lines overwritten before they were written
+    pub fn evicted(&self) -> u64 {
+        self.evicted
+    }
+
+    /// Number of bytes passed to the writer, less those it has not delivered
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written.saturating_sub(self.writer.undelivered_bytes())
+    }
+
+    /// Number of lines the writer accepted and then discarded
+    pub fn undelivered(&self) -> u64 {
+        self.writer.dropped()
+    }
+
+    /// Copy of the buffered lines, oldest first, leaving the buffer untouched
+    pub fn snapshot(&self) -> std::vec::Vec<String> {
+        (self.get_min_external_index_in_buffer()..self.external_index)
+            .filter_map(|k| self.get(k))
+            .collect()
+    }
+
     fn calc_ring_index(&self, external_index: usize) -> usize // Internal index
     {
         external_index & (self.max_size - 1) // max_size must be 1 more than all 1s ex. 0x800
@@ -110,11 +166,7 @@ impl<T: TextDataWriter + Send > CircularStringsBuffer<T>{
 
     fn get_min_external_index_in_buffer(&self) -> usize // External index
     {
-        if self.max_size > self.external_index {
-            0
-        } else {
-            self.external_index - self.max_size
-        }
+        self.external_index.saturating_sub(self.max_size)
     }
 
     fn get_qty_in_buffer(&self) -> usize {
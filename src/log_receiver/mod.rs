@@ -2,8 +2,16 @@
 
 
 use super::log_common;
+use super::log_channel;
+use super::log_stats;
 
 pub mod circular_buffer;
+pub mod sink;
+pub mod run_marker;
+mod trigger;
+
+pub use sink::Sink;
+pub use trigger::TriggerPolicy;
 
 /// How often to stop waiting in receive and check state
 const POLLING_RECV_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(1000);
@@ -51,104 +59,351 @@ impl BufferSize{
   }
 }
 
+/// Settings for a `LogReceiver` beyond its level, sinks and buffer size
+pub(crate) struct ReceiverOptions {
+    pub trigger_policy: TriggerPolicy,
+    /// Counters of the senders, so stats can be reported by the receiver
+    pub sender_counters: std::sync::Arc<log_stats::SenderCounters>,
+    /// Write stats into the header of each dump
+    pub is_stats_in_header: bool,
+    /// Path of the marker of this run, written once the sinks are active and cleared on shutdown
+    pub run_marker: Option<std::path::PathBuf>,
+}
+
 /// Spawn a thread containing a `LogReceiver` and buffer for messages
-pub fn spawn(
+/// 
+/// Fails if `log_dump_level` is `Off`, a sink can not be activated or the run marker can not be written.
+pub(crate) fn spawn(
     log_dump_level: log_common::Level,
-    receiver: std::sync::mpsc::Receiver<log_common::LogData>,
-    log_file_path: std::path::PathBuf,
+    receiver: log_channel::ChannelReceiver,
+    sinks: std::vec::Vec<Sink>,
     buffer_size: BufferSize,
-) -> Option<std::thread::JoinHandle<()>> {
+    options: ReceiverOptions,
+) -> Result<std::thread::JoinHandle<()>, &'static str> {
     if log_common::Level::Off != log_dump_level {
         debug_assert!( buffer_size.is_valid() );
 
-        let mut buffer = std::vec::Vec::<String>::with_capacity(buffer_size.value());
-        for _k in 0..buffer_size.value() {
-            buffer.push(String::new());
-        }
+        let active_sinks = sinks.into_iter()
+            .map(|s| s.activate(buffer_size))
+            .collect::<Result<std::vec::Vec<_>, _>>()?;
 
-        let text_data_writer = circular_buffer::data_writer::DataWriter::new();
-        let circle = circular_buffer::CircularStringsBuffer::new(buffer, text_data_writer);
+        // Only written once nothing else can fail, so a failed start does not leave it behind
+        let (run_marker, previous_run) = match &options.run_marker {
+            Some(path) => {
+                let (marker, previous) = run_marker::RunMarker::create(path)?;
+                (Some(marker), previous)
+            }
+            None => (None, None),
+        };
 
-        Some(std::thread::spawn(move || {    
-            let mut logger = LogReceiver::new(log_dump_level, receiver, log_file_path, circle);
-            logger.execute();
+        Ok(std::thread::spawn(move || {    
+            let mut logger = LogReceiver::new(log_dump_level, receiver, active_sinks);
+            logger.trigger.set_policy(options.trigger_policy);
+            logger.sender_counters = options.sender_counters;
+            logger.is_stats_in_header = options.is_stats_in_header;
+            logger.run_marker = run_marker;
+            logger.recover();
+            logger.pending_report = previous_run.map(|previous| previous.report());
+            logger.supervise();
         })) // returns thread handle
 
     } else {
-        None
+        Err("Receiver is not needed when dump level is Off")
     }
 }
 
-struct LogReceiver<T: circular_buffer::TextDataWriter + Send> {
+struct LogReceiver {
     log_dump_level: log_common::Level,
-    receiver: std::sync::mpsc::Receiver<log_common::LogData>,
-    log_file_path: std::path::PathBuf,
-    buffer: circular_buffer::CircularStringsBuffer::<T>,
+    /// Dropped before `receiver`, so sinks are closed by the time senders see the disconnect
+    sinks: std::vec::Vec<sink::ActiveSink>,
+    receiver: log_channel::ChannelReceiver,
+    trigger: trigger::Trigger,
+    is_paused: bool,
+    sender_counters: std::sync::Arc<log_stats::SenderCounters>,
+    /// Counters kept by the receiver, sender and sink counters are filled in by `stats()`
+    stats: log_stats::Stats,
+    /// Messages dropped by the channel not yet reported in a dump
+    unreported_dropped: u64,
+    is_stats_in_header: bool,
+    run_marker: Option<run_marker::RunMarker>,
+    /// Error to buffer and dump when `execute()` next starts, such as a restart after a panic
+    pending_report: Option<String>,
+    /// Index of the sink being called, so a panic can be blamed on it
+    current_sink: Option<usize>,
+    /// Panics of each sink since it last completed a dump
+    sink_panics: std::vec::Vec<u32>,
 }
 
-impl<T: circular_buffer::TextDataWriter + Send> LogReceiver<T> {
+impl LogReceiver {
     fn new(
         log_dump_level: log_common::Level,
-        receiver: std::sync::mpsc::Receiver<log_common::LogData>,
-        log_file_path: std::path::PathBuf,
-        buffer: circular_buffer::CircularStringsBuffer::<T>,
+        receiver: log_channel::ChannelReceiver,
+        sinks: std::vec::Vec<sink::ActiveSink>,
     ) -> Self {
         Self {
             log_dump_level,
             receiver,
-            log_file_path,
-            buffer,
+            sink_panics: vec![0; sinks.len()],
+            sinks,
+            trigger: trigger::Trigger::new(TriggerPolicy::Immediate),
+            is_paused: false,
+            sender_counters: std::sync::Arc::default(),
+            stats: log_stats::Stats::default(),
+            unreported_dropped: 0,
+            is_stats_in_header: false,
+            run_marker: None,
+            pending_report: None,
+            current_sink: None,
+        }
+    }
+
+    /// Write lines recovered from a previous run that crashed
+    /// 
+    /// Failures are buffered as `Error` messages so they appear in the next dump.
+    fn recover(&mut self) {
+        let mut failures = std::vec::Vec::<String>::new();
+        for s in self.sinks.iter_mut() {
+            if let Err(e) = s.recover() {
+                failures.push(format!("Sink '{}' failed to write recovered lines: {}", s.name(), e));
+            }
+        }
+        for f in failures {
+            self.stats.dump_failures += 1;
+            self.record_failure(&f);
+            self.push(&log_common::LogData::new(log_common::Level::Error, log_common::get_time_now(), f));
+        }
+    }
+
+    /// Run `execute()`, restarting it if it panics
+    /// 
+    /// A panic, such as from a bug in a sink, would otherwise end the thread
+    /// and every later message would be lost.
+    /// The channel is kept so senders carry on without noticing,
+    /// sinks get fresh buffers since their contents may be inconsistent,
+    /// and the restart is reported in a dump by the restarted `execute()`, so a panic there is caught too.
+    /// A sink that panics again before it completes a dump, such as from a writer that panics on every write,
+    /// is removed so the remaining sinks keep working.
+    fn supervise(&mut self) {
+        const RESTART_DELAY: std::time::Duration = std::time::Duration::from_millis(100);
+
+        loop {
+            let ans = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| self.execute()));
+            if let Err(payload) = ans {
+                let reason = if let Some(s) = payload.downcast_ref::<&str>() {
+                    s.to_string()
+                } else if let Some(s) = payload.downcast_ref::<String>() {
+                    s.clone()
+                } else {
+                    String::from("unknown panic")
+                };
+                let mut report = format!("Receiver restarted after panic: {}", reason);
+                if let Some(k) = self.current_sink.take() {
+                    self.sink_panics[k] += 1;
+                    if 2 <= self.sink_panics[k] {
+                        self.sink_panics.remove(k);
+                        let s = self.sinks.remove(k);
+                        report += &format!(", sink '{}' removed after panicking twice", s.name());
+                    }
+                }
+
+                std::thread::sleep(RESTART_DELAY); // Avoid spinning if every restart panics
+                for s in self.sinks.iter_mut() {
+                    s.reset();
+                }
+                self.stats.restarts += 1;
+                self.stats.last_error = Some(report.clone());
+                self.pending_report = Some(report);
+            } else {
+                break;
+            }
         }
     }
 
     /// Main loop for receiver thread 
     /// 
     /// Will return on two conditions
-    /// * If message payload has log level `log_common::Level::Off`
+    /// * If a `Shutdown` message is received
     /// * If MPSC connection drops indicating there is nothing connected at the other end
     /// 
+    /// `Reconfigure` messages change the dump level, pause state or trigger policy.
+    /// `Query` messages are answered on the reply channel they carry.
+    /// 
     fn execute(&mut self) {
-        debug_assert!(log_common::Level::Off != self.log_dump_level); // execute should not be called if log_dump_level is Off
+        if let Some(report) = self.pending_report.take() {
+            self.push(&log_common::LogData::new(log_common::Level::Error, log_common::get_time_now(), report));
+            self.dump(None);
+        }
         loop {
             let msg = self.receiver.recv_timeout(POLLING_RECV_TIMEOUT);
-            if let Ok(payload) = msg {
-                self.buffer.push(payload.as_string());
+            match msg {
+                Ok(log_channel::Message::Record(payload)) if log_common::Level::Off == payload.level() => {
+                    // Off is not a message level, senders never send it
+                }
+                Ok(log_channel::Message::Record(payload)) => {
+                    self.stats.received += 1;
+                    let lag = (log_common::get_time_now() - payload.time_stamp()).to_std().unwrap_or_default();
+                    self.stats.max_channel_lag = self.stats.max_channel_lag.max(lag);
 
-                if payload.level() > self.log_dump_level {
-                    // NOP for common case
-                } else if payload.level() == log_common::Level::Off {
-                    // Off is signal to exit thread aka turn off
-                    break;
-                } else if payload.level() <= self.log_dump_level {
-                    self.dump();
+                    self.push(&payload);
+
+                    if payload.level() > self.log_dump_level {
+                        // NOP for common case
+                    } else if self.is_paused {
+                        // Keep buffering, dumps resume later
+                    } else if self.trigger.fire(std::time::Instant::now()) {
+                        self.dump(None);
+                    }
                 }
-            } else if let Err(e) = msg {
-                match e {
-                    std::sync::mpsc::RecvTimeoutError::Timeout => (), // On timeout, just go around for another try
-                    std::sync::mpsc::RecvTimeoutError::Disconnected => {
-                        let t = log_common::get_time_now().to_rfc3339();
-                        self.buffer.push(format!(
-                            "{} | Error | Ending logger thread due to MPSC Disconnected",
-                            t
-                        ));
-                        self.dump();
-                        break;
+                Ok(log_channel::Message::Shutdown) => {
+                    if let Some(m) = self.run_marker.take() {
+                        m.clear();
                     }
+                    break;
                 }
-            } else {
-                unreachable!();
+                Ok(log_channel::Message::Flush(ack)) => {
+                    // Everything sent before the flush has been handled
+                    let _ret = ack.send(());
+                }
+                Ok(log_channel::Message::Dump(reason)) => self.dump(Some(&reason)),
+                Ok(log_channel::Message::Reconfigure(command)) => self.reconfigure(command),
+                Ok(log_channel::Message::Query(query)) => self.answer(query),
+                Err(log_channel::RecvTimeoutError::Timeout) => (), // On timeout, just go around for another try
+                Err(log_channel::RecvTimeoutError::Disconnected) => {
+                    self.push(&log_common::LogData::new(
+                        log_common::Level::Error,
+                        log_common::get_time_now(),
+                        String::from("Ending logger thread due to MPSC Disconnected"),
+                    ));
+                    self.dump(None);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Reply to a sender asking about the receiver
+    /// 
+    /// The sender may have given up waiting, so a failed reply is ignored.
+    fn answer(&mut self, query: log_channel::Query) {
+        match query {
+            log_channel::Query::DumpLevel(reply) => {
+                let _ret = reply.send(self.log_dump_level);
+            }
+            log_channel::Query::Stats(reply) => {
+                let _ret = reply.send(self.stats());
+            }
+            log_channel::Query::Snapshot(reply) => {
+                let _ret = reply.send(self.sinks.iter().filter_map(|s| s.snapshot()).collect());
+            }
+        }
+    }
+
+    /// Current stats of senders, channel, receiver and sinks
+    fn stats(&mut self) -> log_stats::Stats {
+        self.take_dropped();
+        log_stats::Stats {
+            evicted: self.sinks.iter().map(|s| s.evicted()).sum(),
+            undelivered: self.sinks.iter().map(|s| s.undelivered()).sum(),
+            bytes_written: self.sinks.iter().map(|s| s.bytes_written()).sum(),
+            ..self.sender_counters.stats()
+        }.merge_receiver(&self.stats)
+    }
+
+    /// Collect messages dropped by the channel, returns the number not yet reported in a dump
+    fn take_dropped(&mut self) -> u64 {
+        let dropped = self.receiver.take_dropped();
+        self.stats.dropped += dropped;
+        self.unreported_dropped += dropped;
+        self.unreported_dropped
+    }
+
+    /// Keep sink failure as the last error, reported through `stats`
+    fn record_failure(&mut self, failure: &str) {
+        self.stats.last_error = Some(failure.to_string());
+    }
+
+    /// Apply runtime change requested by a sender
+    fn reconfigure(&mut self, command: log_channel::Reconfigure) {
+        match command {
+            log_channel::Reconfigure::SetDumpLevel(level) => self.log_dump_level = level,
+            log_channel::Reconfigure::PauseDumps => self.is_paused = true,
+            log_channel::Reconfigure::ResumeDumps => self.is_paused = false,
+            log_channel::Reconfigure::SetTriggerPolicy(policy) => self.trigger.set_policy(policy),
+        }
+    }
+
+    /// Buffer message in every sink, or write it through for streaming sinks
+    /// 
+    /// A failing streaming sink is reported as an `Error` message in the buffered sinks.
+    fn push(&mut self, payload: &log_common::LogData) {
+        let mut failures = std::vec::Vec::<String>::new();
+        for (k, s) in self.sinks.iter_mut().enumerate() {
+            self.current_sink = Some(k);
+            if let Err(e) = s.push(payload) {
+                failures.push(format!("Sink '{}' failed: {}", s.name(), e));
+            }
+        }
+        for f in failures {
+            self.stats.stream_failures += 1;
+            self.record_failure(&f);
+            let d = log_common::LogData::new(log_common::Level::Error, log_common::get_time_now(), f);
+            for (k, s) in self.sinks.iter_mut().enumerate().filter(|(_, s)| !s.is_streaming()) {
+                self.current_sink = Some(k);
+                let _r = s.push(&d); // Buffered sinks can not fail to push
             }
         }
+        self.current_sink = None;
     }
 
-    /// Dump all buffered data to output file
+    /// Dump all buffered data to every sink
     /// 
-    /// Clears buffer after write
-    fn dump(&mut self) {
-        let _r = self.buffer.write_to_file_and_clear(&self.log_file_path);
-        #[cfg(debug_assertions)]
-        if let Err(e) = _r {
-            println!("Error: write_to_file_and_clear() returned: {}", e);
+    /// Clears buffers after write.
+    /// Messages lost due to a full channel since the last dump are reported first.
+    /// The header has the reason for requested dumps, and stats if enabled.
+    /// A failing sink does not stop the remaining sinks from being written.
+    /// Each failure is buffered as an `Error` message so it appears in the next dump.
+    fn dump(&mut self, reason: Option<&str>) {
+        let dropped = self.take_dropped();
+        if 0 < dropped {
+            self.unreported_dropped = 0;
+            self.push(&log_common::LogData::new(
+                log_common::Level::Warn,
+                log_common::get_time_now(),
+                format!("{} messages were dropped because the channel was full", dropped),
+            ));
+        }
+        self.stats.dumps += 1;
+
+        let header = if reason.is_some() || self.is_stats_in_header {
+            let mut h = circular_buffer::DUMP_HEADER.to_string();
+            if let Some(r) = reason {
+                h += &format!("Dump requested: {}\n", r);
+            }
+            if self.is_stats_in_header {
+                h += &format!("Stats: {}\n", self.stats());
+            }
+            Some(h)
+        } else {
+            None
+        };
+
+        let mut failures = std::vec::Vec::<String>::new();
+        for (k, s) in self.sinks.iter_mut().enumerate() {
+            self.current_sink = Some(k);
+            if let Err(e) = s.dump(header.as_deref()) {
+                failures.push(format!("Sink '{}' failed: {}", s.name(), e));
+            }
+            self.sink_panics[k] = 0;
+        }
+        self.current_sink = None;
+        for f in failures {
+            self.stats.dump_failures += 1;
+            self.record_failure(&f);
+            self.push(&log_common::LogData::new(
+                log_common::Level::Error,
+                log_common::get_time_now(),
+                f,
+            ));
         }
     }
 }
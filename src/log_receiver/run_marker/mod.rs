@@ -0,0 +1,69 @@
+// run marker module
+//
+// File present while a logger is running, removed on clean shutdown.
+// Finding it on start means the previous run ended without shutting down.
+
+#[cfg(test)]
+mod run_marker_tests;
+
+/// Previous run that did not shut down cleanly
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreviousRun {
+    pub pid: Option<u32>,
+    /// Start time as RFC 3339
+    pub started: Option<String>,
+}
+
+impl PreviousRun {
+    /// Incident message for the dump
+    pub fn report(&self) -> String {
+        format!(
+            "Previous run terminated uncleanly: pid {}, started {}",
+            self.pid.map(|p| p.to_string()).unwrap_or_else(|| String::from("unknown")),
+            self.started.as_deref().unwrap_or("unknown"),
+        )
+    }
+
+    fn parse(text: &str) -> Self {
+        let mut ans = Self { pid: None, started: None };
+        for line in text.lines() {
+            if let Some(v) = line.strip_prefix("pid=") {
+                ans.pid = v.trim().parse().ok();
+            } else if let Some(v) = line.strip_prefix("started=") {
+                ans.started = Some(v.trim().to_string());
+            }
+        }
+        ans
+    }
+}
+
+
+/// Marker file of the current run
+#[derive(Debug)]
+pub struct RunMarker {
+    path: std::path::PathBuf,
+}
+
+impl RunMarker {
+
+    /// Write marker for this run, returning the previous run if its marker was still present
+    pub fn create(path: &std::path::Path) -> Result<(Self, Option<PreviousRun>), &'static str> {
+        let previous = std::fs::read_to_string(path).ok().map(|t| PreviousRun::parse(&t));
+
+        let text = format!(
+            "pid={}\nstarted={}\n",
+            std::process::id(),
+            super::log_common::get_time_now().to_rfc3339(),
+        );
+        if std::fs::write(path, text).is_err() {
+            Err("Failed to write run marker file")
+        } else {
+            Ok((Self { path: path.to_path_buf() }, previous))
+        }
+    }
+
+    /// Remove marker, the run ended cleanly
+    pub fn clear(self) {
+        let _ret = std::fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,41 @@
+#![cfg(test)]
+
+use super::*;
+
+
+#[test]
+fn test_create_and_clear() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(rw_dir).join("test_run_marker.running");
+    let _ret = std::fs::remove_file(&fp);
+
+    {
+        let (_marker, previous) = RunMarker::create(&fp).unwrap();
+        assert!(previous.is_none());
+        assert!(fp.exists());
+        // Not cleared, as if the process was killed
+    }
+
+    let (marker, previous) = RunMarker::create(&fp).unwrap();
+    let previous = previous.unwrap();
+    assert!(Some(std::process::id()) == previous.pid);
+    assert!(previous.started.is_some());
+
+    marker.clear();
+    assert!(!fp.exists());
+    let (marker, previous) = RunMarker::create(&fp).unwrap();
+    assert!(previous.is_none());
+    marker.clear();
+}
+
+
+#[test]
+fn test_report() {
+    let previous = PreviousRun::parse("pid=42\nstarted=2024-01-02T03:04:05+00:00\n");
+    assert!("Previous run terminated uncleanly: pid 42, started 2024-01-02T03:04:05+00:00" == previous.report());
+
+    // Damaged marker still reports the crash
+    let previous = PreviousRun::parse("garbage");
+    assert!("Previous run terminated uncleanly: pid unknown, started unknown" == previous.report());
+}
@@ -0,0 +1,84 @@
+// journald module
+
+use super::log_common;
+use super::syslog;
+use super::Formatter;
+
+/// Socket systemd-journald listens on for the native protocol
+pub const DEFAULT_JOURNALD_PATH: &str = "/run/systemd/journal/socket";
+
+/// Separates fields in lines produced by [`formatter`]
+/// 
+/// `JournaldWriter` splits lines on this character and
+/// encodes each field in the native protocol.
+pub(crate) const FIELD_SEPARATOR: char = '\0';
+
+/// Make a valid journal field name
+/// 
+/// Field names may only contain `A-Z`, `0-9` and `_`,
+/// must not start with `_` or a digit, and are at most 64 characters.
+/// Returns `None` if nothing usable remains.
+pub fn field_name(name: &str) -> Option<String> {
+    const MAX_FIELD_NAME_LENGTH: usize = 64;
+
+    let mut ans: String = name
+        .trim_start_matches('_')
+        .chars()
+        .map(|c| {
+            let c = c.to_ascii_uppercase();
+            if c.is_ascii_uppercase() || c.is_ascii_digit() { c } else { '_' }
+        })
+        .collect();
+
+    if ans.starts_with(|c: char| c.is_ascii_digit()) {
+        ans.insert_str(0, "F_");
+    }
+    ans.truncate(MAX_FIELD_NAME_LENGTH);
+
+    if ans.is_empty() {
+        None
+    } else {
+        Some(ans)
+    }
+}
+
+/// Append `NAME=value` to line, starting a new field if line is not empty
+fn push_field(line: &mut String, name: &str, value: &str) {
+    if !line.is_empty() {
+        line.push(FIELD_SEPARATOR);
+    }
+    line.push_str(name);
+    line.push('=');
+    line.extend(value.chars().map(|c| if FIELD_SEPARATOR == c { ' ' } else { c }));
+}
+
+/// Build a formatter producing the journal fields of each log message
+/// 
+/// Fields written: `MESSAGE`, `PRIORITY`, `SYSLOG_IDENTIFIER`, `LOG_TIMESTAMP`,
+/// `CODE_FILE` and `CODE_LINE` if the location is known,
+/// followed by the custom fields of the message.
+/// 
+/// Output is only meaningful to `JournaldWriter` which adds `INCIDENT_ID`.
+pub fn formatter(identifier: &str) -> Formatter {
+    let identifier = identifier.to_string();
+
+    Box::new(move |d: &log_common::LogData| {
+        let mut line = String::new();
+        push_field(&mut line, "MESSAGE", d.line());
+        push_field(&mut line, "PRIORITY", &syslog::severity(d.level()).to_string());
+        if !identifier.is_empty() {
+            push_field(&mut line, "SYSLOG_IDENTIFIER", &identifier);
+        }
+        push_field(&mut line, "LOG_TIMESTAMP", &d.time_stamp().to_rfc3339());
+        if let Some(l) = d.location() {
+            push_field(&mut line, "CODE_FILE", &l.file);
+            push_field(&mut line, "CODE_LINE", &l.line.to_string());
+        }
+        for (k, v) in d.fields() {
+            if let Some(name) = field_name(k) {
+                push_field(&mut line, &name, v);
+            }
+        }
+        line
+    })
+}
@@ -0,0 +1,374 @@
+// sink module
+
+use super::log_common;
+use super::circular_buffer;
+use super::BufferSize;
+
+#[cfg(unix)]
+pub mod syslog;
+#[cfg(unix)]
+pub mod journald;
+
+#[cfg(test)]
+mod sink_tests;
+
+/// Function used by a sink to turn a [`log_common::LogData`] into a line of text
+pub type Formatter = Box<dyn Fn(&log_common::LogData) -> String + Send>;
+
+/// Destination that buffered messages are dumped to
+///
+/// Each sink has its own writer, formatter and minimum level.
+/// Messages less severe than the sink's minimum level are not
+/// buffered for that sink.
+pub struct Sink {
+    name: String,
+    destination: std::path::PathBuf,
+    writer: Box<dyn circular_buffer::TextDataWriter + Send>,
+    formatter: Formatter,
+    min_level: log_common::Level,
+    is_streaming: bool,
+    /// File the buffer is mirrored in to survive a crash
+    crash_file: Option<std::path::PathBuf>,
+}
+
+impl Sink {
+
+    /// Construct a sink from any [`circular_buffer::TextDataWriter`]
+    ///
+    /// `destination` is passed to the writer's `open()` on every dump.
+    /// Defaults to the text formatter and minimum level `Trace`.
+    pub fn new(
+        name: &str,
+        destination: std::path::PathBuf,
+        writer: Box<dyn circular_buffer::TextDataWriter + Send>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            destination,
+            writer,
+            formatter: text_formatter(),
+            min_level: log_common::Level::Trace,
+            is_streaming: false,
+            crash_file: None,
+        }
+    }
+
+    /// Construct a sink appending to a plain text file
+    ///
+    /// Path such as `"./my_log.txt"` or `"./my_log.log"`
+    pub fn file(log_file_path: &str) -> Result<Self, &'static str> {
+        let fp = std::path::PathBuf::from(log_file_path);
+
+        if fp.is_dir() {
+            Err("File path should be a plain file, not a directory")
+        } else if fp.parent().is_none() {
+            Err("Log file location does not seem to be valid. Are you trying to write to root?")
+        } else {
+            let writer = circular_buffer::data_writer::DataWriter::new();
+            Ok(Self::new(log_file_path, fp, Box::new(writer)))
+        }
+    }
+
+    /// Construct a sink writing to standard error
+    pub fn stderr() -> Self {
+        let writer = circular_buffer::data_writer::StderrWriter::new();
+        Self::new("stderr", std::path::PathBuf::new(), Box::new(writer))
+    }
+
+    /// Construct a sink shipping each message to a collector over TCP or UDP
+    /// 
+    /// `address` such as `"10.0.0.5:5140"`.
+    /// Network delivery is best effort, add a file sink as well to keep a durable copy.
+    pub fn network(address: &str, protocol: circular_buffer::data_writer::NetworkProtocol) -> Self {
+        const OUTBOX_CAPACITY: usize = 4096; // Frames held while the collector is unreachable
+        let writer = circular_buffer::data_writer::NetworkWriter::new(protocol, OUTBOX_CAPACITY);
+        Self::new("network", std::path::PathBuf::from(address), Box::new(writer))
+    }
+
+    /// Construct a sink sending each message to the local syslog daemon
+    /// 
+    /// `socket_path` is normally [`syslog::DEFAULT_SYSLOG_PATH`].
+    /// Messages are sent as Unix datagrams, one per log message.
+    #[cfg(unix)]
+    pub fn syslog(
+        socket_path: &str,
+        format: syslog::SyslogFormat,
+        facility: syslog::Facility,
+        app_name: &str,
+    ) -> Self {
+        let writer = circular_buffer::data_writer::DatagramWriter::new();
+        Self::new("syslog", std::path::PathBuf::from(socket_path), Box::new(writer))
+            .with_formatter(syslog::formatter(format, facility, app_name))
+    }
+
+    /// Construct a sink writing each message to the systemd journal
+    /// 
+    /// `socket_path` is normally [`journald::DEFAULT_JOURNALD_PATH`].
+    /// Each message becomes one journal entry with `PRIORITY`, `CODE_FILE`, `CODE_LINE`
+    /// and custom fields, plus an `INCIDENT_ID` shared by all entries of a dump.
+    #[cfg(unix)]
+    pub fn journald(socket_path: &str, identifier: &str) -> Self {
+        let writer = circular_buffer::data_writer::JournaldWriter::new();
+        Self::new("journald", std::path::PathBuf::from(socket_path), Box::new(writer))
+            .with_formatter(journald::formatter(identifier))
+    }
+
+    /// Replace the formatter used to turn messages into lines
+    pub fn with_formatter(mut self, formatter: Formatter) -> Self {
+        self.formatter = formatter;
+        self
+    }
+
+    /// Set the least severe level this sink will buffer
+    ///
+    /// For example:
+    /// min_level = Warn
+    /// Will buffer messages of type Warn, Error
+    pub fn with_min_level(mut self, min_level: log_common::Level) -> Self {
+        self.min_level = min_level;
+        self
+    }
+
+    /// Write messages through to this sink as they arrive instead of buffering them
+    /// 
+    /// Every message at least as severe as the sink's minimum level is written immediately,
+    /// like a conventional logger. Streaming sinks are not written on dump,
+    /// the buffer continues to be dumped to the other sinks.
+    pub fn streaming(mut self) -> Self {
+        self.is_streaming = true;
+        self
+    }
+
+    /// Mirror the buffer in a memory mapped file so it survives the process being killed
+    /// 
+    /// If the previous run using `crash_file` did not shut down cleanly,
+    /// such as after a `SIGKILL` or abort, the lines it had buffered but not dumped
+    /// are written to this sink on start, under a "Recovered from previous crash" header.
+    /// Lines longer than [`CRASH_SLOT_SIZE`] bytes are truncated in the mirror.
+    /// Has no effect on streaming sinks, which have no buffer.
+    pub fn crash_safe(mut self, crash_file: &str) -> Self {
+        self.crash_file = Some(std::path::PathBuf::from(crash_file));
+        self
+    }
+
+    /// Name used when reporting failures of this sink
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Allocate the buffer for this sink so it can be used by a `LogReceiver`
+    /// 
+    /// Streaming sinks do not get a buffer.
+    /// Fails if the crash file can not be opened.
+    pub(crate) fn activate(self, buffer_size: BufferSize) -> Result<ActiveSink, &'static str> {
+        let mut recovered = vec!();
+        let mode = if self.is_streaming {
+            SinkMode::Streaming { writer: self.writer, is_open: false }
+        } else {
+            debug_assert!( buffer_size.is_valid() );
+
+            let mut buffer = std::vec::Vec::<String>::with_capacity(buffer_size.value());
+            for _k in 0..buffer_size.value() {
+                buffer.push(String::new());
+            }
+            let mut buffer = circular_buffer::CircularStringsBuffer::new(buffer, self.writer);
+            if let Some(path) = &self.crash_file {
+                let (ring, lines) = circular_buffer::mmap_ring::MmapRing::open(path, buffer_size.value(), CRASH_SLOT_SIZE)?;
+                buffer = buffer.with_mirror(ring);
+                recovered = lines;
+            }
+            SinkMode::Buffered(buffer)
+        };
+
+        Ok(ActiveSink {
+            name: self.name,
+            destination: self.destination,
+            formatter: self.formatter,
+            min_level: self.min_level,
+            mode,
+            bytes_streamed: 0,
+            recovered,
+        })
+    }
+}
+
+
+/// Bytes stored for each line in a crash file, see [`Sink::crash_safe`]
+pub const CRASH_SLOT_SIZE: usize = 1024;
+
+
+/// Text formatter
+///
+/// Output = "<timestamp> | <log level> | <payload line>"
+pub fn text_formatter() -> Formatter {
+    Box::new(|d| d.as_string())
+}
+
+/// JSON formatter, one object per line
+pub fn json_formatter() -> Formatter {
+    Box::new(|d| d.as_json())
+}
+
+
+/// Lines buffered by a sink at the time of a snapshot
+#[derive(Debug, Clone, PartialEq)]
+pub struct SinkSnapshot {
+    /// Name of the sink
+    pub name: String,
+    /// Formatted lines, oldest first
+    pub lines: std::vec::Vec<String>,
+}
+
+
+/// How an active sink handles messages
+enum SinkMode {
+    /// Buffer messages and write them on dump
+    Buffered(circular_buffer::CircularStringsBuffer<Box<dyn circular_buffer::TextDataWriter + Send>>),
+    /// Write each message as it arrives, keeping the writer open between messages
+    Streaming {
+        writer: Box<dyn circular_buffer::TextDataWriter + Send>,
+        is_open: bool,
+    },
+}
+
+/// Sink with its buffer allocated, owned by the receiver thread
+pub(crate) struct ActiveSink {
+    name: String,
+    destination: std::path::PathBuf,
+    formatter: Formatter,
+    min_level: log_common::Level,
+    mode: SinkMode,
+    /// Bytes written while streaming
+    bytes_streamed: u64,
+    /// Lines from a previous run that crashed, not yet written
+    recovered: std::vec::Vec<String>,
+}
+
+impl ActiveSink {
+
+    /// Handle message if it is at least as severe as this sink's minimum level
+    /// 
+    /// Buffered sinks store the message, streaming sinks write it immediately.
+    pub fn push(&mut self, data: &log_common::LogData) -> Result<(), &'static str> {
+        if data.level() > self.min_level {
+            return Ok(());
+        }
+        let line = (self.formatter)(data);
+        match &mut self.mode {
+            SinkMode::Buffered(buffer) => {
+                buffer.push(line);
+                Ok(())
+            }
+            SinkMode::Streaming { writer, is_open } => {
+                if !*is_open {
+                    if writer.open(&self.destination).is_err() {
+                        return Err("Stream open failed");
+                    }
+                    *is_open = true;
+                }
+                if writer.write(&line).is_err() {
+                    // Reopen on next message
+                    writer.close();
+                    *is_open = false;
+                    Err("Stream failed to write data")
+                } else {
+                    self.bytes_streamed += line.len() as u64;
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    /// Write all buffered data to destination and clear buffer
+    /// 
+    /// `header` replaces the default dump header.
+    /// Nothing to do for streaming sinks.
+    pub fn dump(&mut self, header: Option<&str>) -> Result<(), &'static str> {
+        match (&mut self.mode, header) {
+            (SinkMode::Buffered(buffer), None) => buffer.write_to_file_and_clear(&self.destination),
+            (SinkMode::Buffered(buffer), Some(h)) => buffer.write_to_file_and_clear_with_header(&self.destination, h),
+            (SinkMode::Streaming { .. }, _) => Ok(()),
+        }
+    }
+
+    /// Copy of the buffered lines, `None` for streaming sinks which have no buffer
+    pub fn snapshot(&self) -> Option<SinkSnapshot> {
+        match &self.mode {
+            SinkMode::Buffered(buffer) => Some(SinkSnapshot {
+                name: self.name.clone(),
+                lines: buffer.snapshot(),
+            }),
+            SinkMode::Streaming { .. } => None,
+        }
+    }
+
+    /// Write lines recovered from a previous run that crashed, if any
+    pub fn recover(&mut self) -> Result<(), &'static str> {
+        let recovered = std::mem::take(&mut self.recovered);
+        match &mut self.mode {
+            SinkMode::Buffered(buffer) if !recovered.is_empty() => {
+                for line in recovered {
+                    buffer.push(line);
+                }
+                let header = format!("{}Recovered from previous crash\n", circular_buffer::DUMP_HEADER);
+                buffer.write_to_file_and_clear_with_header(&self.destination, &header)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Discard buffered lines and close any open stream
+    pub fn reset(&mut self) {
+        match &mut self.mode {
+            SinkMode::Buffered(buffer) => buffer.reset(),
+            SinkMode::Streaming { writer, is_open } => {
+                if *is_open {
+                    writer.close();
+                    *is_open = false;
+                }
+            }
+        }
+    }
+
+    /// Number of buffered lines overwritten before they were written
+    pub fn evicted(&self) -> u64 {
+        match &self.mode {
+            SinkMode::Buffered(buffer) => buffer.evicted(),
+            SinkMode::Streaming { .. } => 0,
+        }
+    }
+
+    /// Number of bytes written by this sink
+    /// 
+    /// Lines a writer holds back, such as a network outbox while the collector is down, are not counted.
+    pub fn bytes_written(&self) -> u64 {
+        match &self.mode {
+            SinkMode::Buffered(buffer) => buffer.bytes_written(),
+            SinkMode::Streaming { writer, .. } => self.bytes_streamed.saturating_sub(writer.undelivered_bytes()),
+        }
+    }
+
+    /// Number of lines the writer accepted and then discarded
+    pub fn undelivered(&self) -> u64 {
+        match &self.mode {
+            SinkMode::Buffered(buffer) => buffer.undelivered(),
+            SinkMode::Streaming { writer, .. } => writer.dropped(),
+        }
+    }
+
+    pub fn is_streaming(&self) -> bool {
+        matches!(self.mode, SinkMode::Streaming { .. })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Drop for ActiveSink {
+    fn drop(&mut self) {
+        if let SinkMode::Streaming { writer, is_open: true } = &mut self.mode {
+            writer.close();
+        }
+    }
+}
@@ -0,0 +1,373 @@
+#![cfg(test)]
+
+use super::*;
+
+
+#[test]
+fn test_syslog_severity() {
+    assert!(3 == syslog::severity(log_common::Level::Error));
+    assert!(4 == syslog::severity(log_common::Level::Warn));
+    assert!(6 == syslog::severity(log_common::Level::Info));
+    assert!(7 == syslog::severity(log_common::Level::Trace));
+}
+
+
+#[test]
+fn test_syslog_formatter() {
+    let ts = log_common::get_time_now();
+    let d = log_common::LogData::new(log_common::Level::Warn, ts, "test line".to_string());
+    let pid = std::process::id();
+
+    // local0 = 16, warning = 4
+    let f = syslog::formatter(syslog::SyslogFormat::Rfc5424, syslog::Facility::Local0, "my app");
+    let line = f(&d);
+    assert!(line.starts_with("<132>1 "));
+    assert!(line.contains(&ts.to_rfc3339()));
+    assert!(line.ends_with(&format!(" my_app {} - - test line", pid)));
+
+    // user = 1, warning = 4
+    let f = syslog::formatter(syslog::SyslogFormat::Rfc3164, syslog::Facility::User, "my_app");
+    let line = f(&d);
+    assert!(line.starts_with("<12>"));
+    assert!(line.ends_with(&format!(" my_app[{}]: test line", pid)));
+}
+
+
+#[test]
+fn test_syslog_sink() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let socket_path = std::path::PathBuf::from(rw_dir).join("test_syslog_sink.sock");
+    let _r = std::fs::remove_file(&socket_path);
+
+    let listener = std::os::unix::net::UnixDatagram::bind(&socket_path).unwrap();
+    listener.set_read_timeout(Some(std::time::Duration::from_secs(5))).unwrap();
+
+    let sink = Sink::syslog(
+        socket_path.to_str().unwrap(),
+        syslog::SyslogFormat::Rfc5424,
+        syslog::Facility::Daemon,
+        "test_app",
+    );
+    let log = crate::LoggerBuilder::new(log_common::Level::Trace, log_common::Level::Error, BufferSize::Size16)
+        .add_sink(sink)
+        .build()
+        .unwrap();
+
+    log.info_str("test line 1");
+    log.error_str("test line 2");
+
+    let mut buf = [0_u8; 1024];
+
+    // Header is not sent, first datagram is the first message
+    let n = listener.recv(&mut buf).unwrap();
+    let line = String::from_utf8_lossy(&buf[..n]).to_string();
+    assert!(line.starts_with("<30>1 ")); // daemon = 3, info = 6
+    assert!(line.ends_with("test line 1"));
+
+    let n = listener.recv(&mut buf).unwrap();
+    let line = String::from_utf8_lossy(&buf[..n]).to_string();
+    assert!(line.starts_with("<27>1 ")); // daemon = 3, err = 3
+    assert!(line.ends_with("test line 2"));
+
+    log.shutdown();
+    let _r = std::fs::remove_file(&socket_path);
+}
+
+
+/// Decode one journald native protocol entry into fields
+fn parse_journal_entry(data: &[u8]) -> std::vec::Vec<(String, String)> {
+    let mut ans = vec!();
+    let mut k = 0;
+    while k < data.len() {
+        let end = k + data[k..].iter().position(|b| b'\n' == *b).unwrap();
+        let line = String::from_utf8_lossy(&data[k..end]).to_string();
+        if let Some((name, value)) = line.split_once('=') {
+            ans.push((name.to_string(), value.to_string()));
+            k = end + 1;
+        } else {
+            // Binary form, NAME\n<u64 length><value>\n
+            let mut len = [0_u8; 8];
+            len.copy_from_slice(&data[end + 1..end + 9]);
+            let len = u64::from_le_bytes(len) as usize;
+            let value = String::from_utf8_lossy(&data[end + 9..end + 9 + len]).to_string();
+            ans.push((line, value));
+            k = end + 9 + len + 1;
+        }
+    }
+    ans
+}
+
+fn journal_field(entry: &[(String, String)], name: &str) -> Option<String> {
+    entry.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone())
+}
+
+
+#[test]
+fn test_journald_field_name() {
+    assert!(Some(String::from("REQUEST_ID")) == journald::field_name("request-id"));
+    assert!(Some(String::from("USER")) == journald::field_name("__user"));
+    assert!(Some(String::from("F_1ST")) == journald::field_name("1st"));
+    assert!(journald::field_name("___").is_none());
+    assert!(64 == journald::field_name(&"a".repeat(100)).unwrap().len());
+}
+
+
+#[test]
+fn test_journald_sink() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let socket_path = std::path::PathBuf::from(rw_dir).join("test_journald_sink.sock");
+    let _r = std::fs::remove_file(&socket_path);
+
+    let listener = std::os::unix::net::UnixDatagram::bind(&socket_path).unwrap();
+    listener.set_read_timeout(Some(std::time::Duration::from_secs(5))).unwrap();
+
+    let sink = Sink::journald(socket_path.to_str().unwrap(), "test_app");
+    let log = crate::LoggerBuilder::new(log_common::Level::Trace, log_common::Level::Error, BufferSize::Size16)
+        .add_sink(sink)
+        .build()
+        .unwrap();
+
+    log.log_fields(log_common::Level::Info, "test line 1\nsecond line", &[("request-id", "42")]);
+    let expected_line = line!() - 1;
+    log.error_str("test line 2");
+
+    let mut buf = [0_u8; 4096];
+
+    let n = listener.recv(&mut buf).unwrap();
+    let first = parse_journal_entry(&buf[..n]);
+    assert!(Some(String::from("test line 1\nsecond line")) == journal_field(&first, "MESSAGE"));
+    assert!(Some(String::from("6")) == journal_field(&first, "PRIORITY"));
+    assert!(Some(String::from("test_app")) == journal_field(&first, "SYSLOG_IDENTIFIER"));
+    assert!(journal_field(&first, "CODE_FILE").unwrap().ends_with("sink_tests/mod.rs"));
+    assert!(Some(expected_line.to_string()) == journal_field(&first, "CODE_LINE"));
+    assert!(Some(String::from("42")) == journal_field(&first, "REQUEST_ID"));
+
+    let n = listener.recv(&mut buf).unwrap();
+    let second = parse_journal_entry(&buf[..n]);
+    assert!(Some(String::from("test line 2")) == journal_field(&second, "MESSAGE"));
+    assert!(Some(String::from("3")) == journal_field(&second, "PRIORITY"));
+
+    // Both entries belong to the same dump
+    let incident = journal_field(&first, "INCIDENT_ID").unwrap();
+    assert!(32 == incident.len());
+    assert!(Some(incident) == journal_field(&second, "INCIDENT_ID"));
+
+    log.shutdown();
+    let _r = std::fs::remove_file(&socket_path);
+}
+
+
+/// Read one length prefixed frame from a TCP stream
+fn read_tcp_frame(stream: &mut std::net::TcpStream) -> String {
+    use std::io::Read;
+    let mut len = [0_u8; 4];
+    stream.read_exact(&mut len).unwrap();
+    let mut data = vec!(0_u8; u32::from_be_bytes(len) as usize);
+    stream.read_exact(&mut data).unwrap();
+    String::from_utf8(data).unwrap()
+}
+
+
+#[test]
+fn test_network_sink_tcp() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+
+    let sink = Sink::network(&address, circular_buffer::data_writer::NetworkProtocol::Tcp);
+    let log = crate::LoggerBuilder::new(log_common::Level::Trace, log_common::Level::Error, BufferSize::Size16)
+        .add_sink(sink)
+        .build()
+        .unwrap();
+
+    log.info_str("test line 1");
+    log.error_str("test line 2");
+
+    let (mut stream, _) = listener.accept().unwrap();
+    stream.set_read_timeout(Some(std::time::Duration::from_secs(5))).unwrap();
+
+    // Header is not sent, first frame is the first message
+    assert!(read_tcp_frame(&mut stream).contains("| Info  | test line 1"));
+    assert!(read_tcp_frame(&mut stream).contains("| Error | test line 2"));
+
+    log.shutdown();
+}
+
+
+#[test]
+fn test_network_writer_reconnect() {
+    use circular_buffer::TextDataWriter;
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = std::path::PathBuf::from(listener.local_addr().unwrap().to_string());
+
+    let mut w = circular_buffer::data_writer::NetworkWriter::new(circular_buffer::data_writer::NetworkProtocol::Tcp, 16);
+    assert!(w.open(&address).is_ok());
+    assert!(w.write("a").is_ok());
+    w.close();
+
+    let (mut stream, _) = listener.accept().unwrap();
+    assert!("a" == read_tcp_frame(&mut stream));
+
+    // Collector drops the connection between dumps
+    drop(stream);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    assert!(w.open(&address).is_ok());
+    assert!(w.write("b").is_ok());
+    w.close();
+
+    let (mut stream, _) = listener.accept().unwrap();
+    assert!("b" == read_tcp_frame(&mut stream));
+    assert!(0 == w.outbox_len());
+}
+
+
+#[test]
+fn test_network_writer_outbox_bound() {
+    use circular_buffer::TextDataWriter;
+    const OUTBOX_CAPACITY: usize = 4;
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = std::path::PathBuf::from(listener.local_addr().unwrap().to_string());
+
+    let mut w = circular_buffer::data_writer::NetworkWriter::new(circular_buffer::data_writer::NetworkProtocol::Tcp, OUTBOX_CAPACITY);
+    assert!(w.open(&address).is_ok());
+
+    // Collector goes away mid dump
+    let (stream, _) = listener.accept().unwrap();
+    drop(stream);
+    drop(listener);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    for k in 0..100 {
+        assert!(w.write(&format!("line {}", k)).is_ok());
+    }
+    w.close();
+
+    assert!(OUTBOX_CAPACITY == w.outbox_len());
+    assert!(0 < w.dropped());
+
+    // Collector still unreachable, the next dump is queued too
+    assert!(w.open(&address).is_ok());
+    assert!(w.write("line 100").is_ok());
+    w.close();
+    assert!(OUTBOX_CAPACITY == w.outbox_len());
+}
+
+
+#[test]
+fn test_network_writer_collector_down() {
+    use circular_buffer::TextDataWriter;
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    drop(listener);
+
+    // Dump while the collector is down is kept
+    let mut w = circular_buffer::data_writer::NetworkWriter::new(circular_buffer::data_writer::NetworkProtocol::Tcp, 16);
+    let path = std::path::PathBuf::from(address.to_string());
+    assert!(w.open(&path).is_ok());
+    assert!(w.write("a").is_ok());
+    w.close();
+    assert!(1 == w.outbox_len());
+
+    // Next dump within the backoff does not try to connect
+    let listener = std::net::TcpListener::bind(address).unwrap();
+    assert!(w.open(&path).is_ok());
+    assert!(w.write("b").is_ok());
+    w.close();
+    assert!(2 == w.outbox_len());
+
+    // After the backoff the queued dumps are sent
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    assert!(w.open(&path).is_ok());
+    w.close();
+    let (mut stream, _) = listener.accept().unwrap();
+    assert!("a" == read_tcp_frame(&mut stream));
+    assert!("b" == read_tcp_frame(&mut stream));
+    assert!(0 == w.outbox_len());
+}
+
+
+#[test]
+fn test_network_writer_reconnect_while_streaming() {
+    use circular_buffer::TextDataWriter;
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = std::path::PathBuf::from(listener.local_addr().unwrap().to_string());
+
+    // Streaming sinks open once and then only write
+    let mut w = circular_buffer::data_writer::NetworkWriter::new(circular_buffer::data_writer::NetworkProtocol::Tcp, 16);
+    assert!(w.open(&address).is_ok());
+    assert!(w.write("a").is_ok());
+
+    let (mut stream, _) = listener.accept().unwrap();
+    assert!("a" == read_tcp_frame(&mut stream));
+
+    // Collector drops the connection, later writes reconnect without another open()
+    drop(stream);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    for k in 0..10 {
+        assert!(w.write(&format!("line {}", k)).is_ok());
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    let (mut stream, _) = listener.accept().unwrap();
+    stream.set_read_timeout(Some(std::time::Duration::from_secs(5))).unwrap();
+    assert!(read_tcp_frame(&mut stream).starts_with("line "));
+    assert!(0 == w.outbox_len());
+    w.close();
+}
+
+
+#[test]
+fn test_network_sink_undelivered_stats() {
+    const OUTBOX_CAPACITY: usize = 4;
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    drop(listener);
+
+    // Collector is down, lines wait in the outbox and are not counted as written
+    let writer = circular_buffer::data_writer::NetworkWriter::new(circular_buffer::data_writer::NetworkProtocol::Tcp, OUTBOX_CAPACITY);
+    let sink = Sink::new("network", std::path::PathBuf::from(address), Box::new(writer)).streaming();
+    let log = crate::LoggerBuilder::new(log_common::Level::Trace, log_common::Level::Error, BufferSize::Size16)
+        .add_sink(sink)
+        .build()
+        .unwrap();
+
+    for k in 0..10 {
+        log.info_string(format!("test line {}", k));
+    }
+
+    let stats = log.stats();
+    assert!(10 - OUTBOX_CAPACITY as u64 == stats.undelivered);
+    assert!(0 == stats.bytes_written);
+    assert!(0 == stats.stream_failures);
+
+    log.shutdown();
+}
+
+
+#[test]
+fn test_network_sink_udp() {
+    let listener = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    listener.set_read_timeout(Some(std::time::Duration::from_secs(5))).unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+
+    let sink = Sink::network(&address, circular_buffer::data_writer::NetworkProtocol::Udp);
+    let log = crate::LoggerBuilder::new(log_common::Level::Trace, log_common::Level::Error, BufferSize::Size16)
+        .add_sink(sink)
+        .build()
+        .unwrap();
+
+    log.error_str("test line 1");
+
+    let mut buf = [0_u8; 1024];
+    let n = listener.recv(&mut buf).unwrap();
+    assert!(String::from_utf8_lossy(&buf[..n]).contains("| Error | test line 1"));
+
+    log.shutdown();
+}
@@ -0,0 +1,111 @@
+// syslog module
+
+use super::log_common;
+use super::Formatter;
+
+/// Socket the local syslog daemon listens on
+pub const DEFAULT_SYSLOG_PATH: &str = "/dev/log";
+
+/// Syslog message layout
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SyslogFormat {
+    /// `<PRI>1 TIMESTAMP HOSTNAME APP-NAME PROCID MSGID SD MSG`
+    Rfc5424,
+    /// `<PRI>Mmm dd hh:mm:ss APP-NAME[PROCID]: MSG`
+    Rfc3164,
+}
+
+/// Syslog facility codes
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(u8)]
+pub enum Facility {
+    Kern   = 0,
+    User   = 1,
+    Mail   = 2,
+    Daemon = 3,
+    Auth   = 4,
+    Syslog = 5,
+    Lpr    = 6,
+    News   = 7,
+    Uucp   = 8,
+    Cron   = 9,
+    Local0 = 16,
+    Local1 = 17,
+    Local2 = 18,
+    Local3 = 19,
+    Local4 = 20,
+    Local5 = 21,
+    Local6 = 22,
+    Local7 = 23,
+}
+
+/// Map log level to syslog severity
+/// 
+/// Error = 3 (err), Warn = 4 (warning), Info = 6 (info), Trace = 7 (debug)
+pub fn severity(level: log_common::Level) -> u8 {
+    match level {
+        log_common::Level::Error => 3,
+        log_common::Level::Warn  => 4,
+        log_common::Level::Info  => 6,
+        log_common::Level::Trace => 7,
+        log_common::Level::Off   => 7,
+    }
+}
+
+/// Read host name of this machine
+/// 
+/// Returns `None` if it can not be determined.
+fn host_name() -> Option<String> {
+    let name = std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .or_else(|_| std::fs::read_to_string("/etc/hostname"))
+        .ok()?;
+    let name = name.trim();
+    if name.is_empty() || name.contains(' ') {
+        None
+    } else {
+        Some(name.to_string())
+    }
+}
+
+/// Build a formatter producing one syslog message per log message
+/// 
+/// `app_name` should not contain spaces, they are replaced with `_`.
+pub fn formatter(format: SyslogFormat, facility: Facility, app_name: &str) -> Formatter {
+    let app_name = if app_name.is_empty() {
+        String::from("-")
+    } else {
+        app_name.replace(' ', "_")
+    };
+    let pid = std::process::id();
+
+    match format {
+        SyslogFormat::Rfc5424 => {
+            let host = host_name().unwrap_or_else(|| String::from("-"));
+            Box::new(move |d| {
+                let pri = (facility as u8) * 8 + severity(d.level());
+                format!(
+                    "<{}>1 {} {} {} {} - - {}",
+                    pri,
+                    d.time_stamp().to_rfc3339(),
+                    host,
+                    app_name,
+                    pid,
+                    d.line()
+                )
+            })
+        }
+        SyslogFormat::Rfc3164 => {
+            Box::new(move |d| {
+                let pri = (facility as u8) * 8 + severity(d.level());
+                format!(
+                    "<{}>{} {}[{}]: {}",
+                    pri,
+                    d.time_stamp().format("%b %e %H:%M:%S"),
+                    app_name,
+                    pid,
+                    d.line()
+                )
+            })
+        }
+    }
+}
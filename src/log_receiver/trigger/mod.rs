@@ -0,0 +1,68 @@
+// trigger module
+
+#[cfg(test)]
+mod trigger_tests;
+
+/// Decides if a message at or above the dump level causes a dump
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TriggerPolicy {
+    /// Every such message causes a dump
+    Immediate,
+    /// Dump on the first such message, then ignore further ones for the given duration.
+    /// Messages keep being buffered and are included in the next dump.
+    Cooldown(std::time::Duration),
+    /// Dump only once `count` such messages arrive within `window`
+    Burst {
+        count: usize,
+        window: std::time::Duration,
+    },
+}
+
+
+/// Trigger policy together with the history it needs
+pub(crate) struct Trigger {
+    policy: TriggerPolicy,
+    last_dump: Option<std::time::Instant>,
+    recent: std::collections::VecDeque<std::time::Instant>,
+}
+
+impl Trigger {
+    pub fn new(policy: TriggerPolicy) -> Self {
+        Self {
+            policy,
+            last_dump: None,
+            recent: std::collections::VecDeque::new(),
+        }
+    }
+
+    /// Replace policy, history is cleared
+    pub fn set_policy(&mut self, policy: TriggerPolicy) {
+        *self = Self::new(policy);
+    }
+
+    /// Record a message at or above the dump level, returns `true` if it should cause a dump
+    pub fn fire(&mut self, now: std::time::Instant) -> bool {
+        let ans = match self.policy {
+            TriggerPolicy::Immediate => true,
+            TriggerPolicy::Cooldown(d) => {
+                self.last_dump.map(|t| now.duration_since(t) >= d).unwrap_or(true)
+            }
+            TriggerPolicy::Burst { count, window } => {
+                self.recent.push_back(now);
+                while self.recent.front().map(|t| now.duration_since(*t) > window).unwrap_or(false) {
+                    self.recent.pop_front();
+                }
+                if self.recent.len() >= count {
+                    self.recent.clear();
+                    true
+                } else {
+                    false
+                }
+            }
+        };
+        if ans {
+            self.last_dump = Some(now);
+        }
+        ans
+    }
+}
@@ -0,0 +1,54 @@
+#![cfg(test)]
+
+use super::*;
+
+const MS: std::time::Duration = std::time::Duration::from_millis(1);
+
+
+#[test]
+fn test_immediate() {
+    let mut trigger = Trigger::new(TriggerPolicy::Immediate);
+    let now = std::time::Instant::now();
+    assert!(trigger.fire(now));
+    assert!(trigger.fire(now));
+}
+
+
+#[test]
+fn test_cooldown() {
+    let mut trigger = Trigger::new(TriggerPolicy::Cooldown(100 * MS));
+    let now = std::time::Instant::now();
+    assert!(trigger.fire(now));
+    assert!(!trigger.fire(now + 50 * MS));
+    assert!(!trigger.fire(now + 99 * MS));
+    assert!(trigger.fire(now + 100 * MS));
+    assert!(!trigger.fire(now + 150 * MS));
+}
+
+
+#[test]
+fn test_burst() {
+    let mut trigger = Trigger::new(TriggerPolicy::Burst { count: 3, window: 100 * MS });
+    let now = std::time::Instant::now();
+    assert!(!trigger.fire(now));
+    assert!(!trigger.fire(now + 10 * MS));
+    assert!(trigger.fire(now + 20 * MS));
+
+    // History is cleared after a dump
+    assert!(!trigger.fire(now + 30 * MS));
+    assert!(!trigger.fire(now + 40 * MS));
+
+    // First one has left the window
+    assert!(!trigger.fire(now + 135 * MS));
+    assert!(trigger.fire(now + 140 * MS));
+}
+
+
+#[test]
+fn test_set_policy() {
+    let mut trigger = Trigger::new(TriggerPolicy::Cooldown(100 * MS));
+    let now = std::time::Instant::now();
+    assert!(trigger.fire(now));
+    trigger.set_policy(TriggerPolicy::Immediate);
+    assert!(trigger.fire(now + MS));
+}
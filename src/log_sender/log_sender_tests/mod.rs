@@ -0,0 +1,154 @@
+#![cfg(test)]
+
+use super::*;
+
+const TIMEOUT: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Line of received record
+fn line(m: Result<log_channel::Message, log_channel::RecvTimeoutError>) -> String {
+    match m {
+        Ok(log_channel::Message::Record(d)) => d.line().to_string(),
+        _ => panic!("expected a record"),
+    }
+}
+
+
+#[test]
+fn test_store_level() {
+    let (sender, mut receiver) = log_channel::unbounded();
+    let log = LogSender::new(sender, log_common::Level::Warn);
+    let clone = log.clone();
+
+    log.info_str("test line 1");
+    log.warn_str("test line 2");
+    assert!("test line 2" == line(receiver.recv_timeout(TIMEOUT)));
+    assert!(receiver.recv_timeout(TIMEOUT).is_err());
+
+    // Changing level on one clone applies to all of them
+    clone.set_store_level(log_common::Level::Trace);
+    assert!(log_common::Level::Trace == log.store_level());
+    log.trace_str("test line 3");
+    assert!("test line 3" == line(receiver.recv_timeout(TIMEOUT)));
+
+    let thread_log = log.clone();
+    std::thread::spawn(move || {
+        thread_log.set_store_level(log_common::Level::Error);
+    }).join().unwrap();
+    assert!(log_common::Level::Error == clone.store_level());
+    clone.warn_str("test line 4");
+    clone.error_str("test line 5");
+    assert!("test line 5" == line(receiver.recv_timeout(TIMEOUT)));
+    assert!(receiver.recv_timeout(TIMEOUT).is_err());
+}
+
+
+#[test]
+fn test_off_not_sent() {
+    let (sender, mut receiver) = log_channel::unbounded();
+    let log = LogSender::new(sender, log_common::Level::Trace);
+
+    // Off is not a message level, it must not reach the receiver
+    log.log_fields(log_common::Level::Off, "test line 1", &[]);
+    log.error_str("test line 2");
+    assert!("test line 2" == line(receiver.recv_timeout(TIMEOUT)));
+    assert!(receiver.recv_timeout(TIMEOUT).is_err());
+}
+
+
+#[test]
+fn test_send_after_receiver_gone() {
+    let (sender, receiver) = log_channel::unbounded();
+    let log = LogSender::new(sender, log_common::Level::Info);
+    assert!(log.try_log(log_common::Level::Info, "test line 1").is_ok());
+    drop(receiver);
+
+    // Must not panic, even in debug builds
+    log.info_str("test line 2");
+    log.log_fields(log_common::Level::Error, "test line 3", &[("k", "v")]);
+    log.pause_dumps();
+    assert!(log.try_log(log_common::Level::Error, "test line 4").is_err());
+
+    // Filtered message is not a failure
+    assert!(log.try_log(log_common::Level::Trace, "test line 5").is_ok());
+
+    let log = log.with_send_failure_policy(SendFailurePolicy::Stderr);
+    log.error_str("test line 6");
+    assert!(1 == log.counters().stats().sent);
+}
+
+
+#[test]
+fn test_disabled_logger_filters() {
+    // Dump level Off, so no receiver is spawned
+    let log = crate::LoggerBuilder::new(log_common::Level::Trace, log_common::Level::Off, crate::BufferSize::Size16)
+        .add_sink(crate::Sink::stderr())
+        .on_send_failure(SendFailurePolicy::Stderr)
+        .build()
+        .unwrap();
+    assert!(log_common::Level::Off == log.store_level());
+    assert!(log.try_log(log_common::Level::Error, "test line 1").is_ok());
+    log.error_str("test line 2");
+
+    let stats = log.counters().stats();
+    assert!(0 == stats.sent);
+    assert!(2 == stats.filtered);
+}
+
+
+#[test]
+fn test_flush_full_ring() {
+    let (sender, mut receiver) = log_channel::per_thread(16);
+    let log = LogSender::new(sender, log_common::Level::Info);
+    for k in 0..20 {
+        log.info_str(&format!("test line {}", k));
+    }
+    // Dropped records are not counted as sent
+    assert!(16 == log.counters().stats().sent);
+
+    let handle = std::thread::spawn(move || {
+        let mut lines = 0;
+        loop {
+            match receiver.recv_timeout(std::time::Duration::from_secs(5)) {
+                Ok(log_channel::Message::Record(_)) => lines += 1,
+                Ok(log_channel::Message::Flush(ack)) => {
+                    assert!(ack.send(()).is_ok());
+                    return (lines, receiver.take_dropped());
+                }
+                _ => panic!("expected records then flush"),
+            }
+        }
+    });
+
+    // Ring is full, flush waits for the receiver to make room rather than being dropped
+    assert!(log.flush().is_ok());
+    let (lines, dropped) = handle.join().unwrap();
+    assert!(16 == lines);
+    assert!(4 == dropped);
+}
+
+
+#[test]
+fn test_flush_timeout() {
+    let (sender, receiver) = log_channel::unbounded();
+    let log = LogSender::new(sender, log_common::Level::Info);
+
+    // Receiver alive but stuck, flush gives up instead of blocking forever
+    assert!(Err("Receiver did not complete flush in time") == log.flush());
+    drop(receiver);
+    assert!(Err("Receiver is not running") == log.flush());
+}
+
+
+#[test]
+fn test_level_from_u8() {
+    let levels = [
+        log_common::Level::Off,
+        log_common::Level::Error,
+        log_common::Level::Warn,
+        log_common::Level::Info,
+        log_common::Level::Trace,
+    ];
+    for l in levels.iter() {
+        assert!(*l == log_common::Level::from_u8(*l as u8));
+    }
+}
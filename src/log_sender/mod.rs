@@ -1,36 +1,291 @@
 // log sender module
 
 use super::log_common;
+use super::log_channel;
+use super::log_receiver;
+use super::log_stats;
+
+#[cfg(test)]
+mod log_sender_tests;
+
+/// Longest a sender waits for the receiver to answer a query
+const QUERY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// What happens to a message sent after the receiver has shut down or died
+/// 
+/// Logging never panics the caller, whatever the policy.
+/// Use [`LogSender::try_log`] to be told about the failure instead.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SendFailurePolicy {
+    /// Message is silently dropped
+    Drop,
+    /// Message is written directly to standard error in the text format
+    Stderr,
+}
 
 #[derive(Clone)]
 pub struct LogSender {
-    log_level: log_common::Level,
-    sender: std::sync::mpsc::Sender<log_common::LogData>,
+    /// Store level as `u8`, shared by all clones
+    log_level: std::sync::Arc<std::sync::atomic::AtomicU8>,
+    sender: log_channel::ChannelSender,
+    /// Counts of sent and filtered messages, shared by all clones
+    counters: std::sync::Arc<log_stats::SenderCounters>,
+    send_failure_policy: SendFailurePolicy,
 }
 
 
 /// LogSender is an object for sending new messages to be buffered
 impl LogSender {
     pub(crate) fn new(
-        sender: std::sync::mpsc::Sender<log_common::LogData>,
+        sender: log_channel::ChannelSender,
         log_level: log_common::Level,
     ) -> Self {
-        Self { sender, log_level }
+        Self {
+            sender,
+            log_level: std::sync::Arc::new(std::sync::atomic::AtomicU8::new(log_level as u8)),
+            counters: std::sync::Arc::default(),
+            send_failure_policy: SendFailurePolicy::Drop,
+        }
+    }
+
+    /// Set what happens to messages that can not be sent
+    pub(crate) fn with_send_failure_policy(mut self, policy: SendFailurePolicy) -> Self {
+        self.send_failure_policy = policy;
+        self
+    }
+
+    /// Counters to be shared with the receiver
+    pub(crate) fn counters(&self) -> std::sync::Arc<log_stats::SenderCounters> {
+        self.counters.clone()
+    }
+
+    /// Read the current store level
+    /// 
+    /// Messages less severe than this level are dropped when sent.
+    pub fn store_level(&self) -> log_common::Level {
+        log_common::Level::from_u8(self.log_level.load(std::sync::atomic::Ordering::Relaxed))
+    }
+
+    /// Change the store level of this sender and every clone of it
+    /// 
+    /// Takes effect for all threads immediately, such as
+    /// turning on `Trace` while chasing a live issue.
+    /// 
+    /// Messages dropped at the sender can not trigger a dump,
+    /// so setting this less severe than the dump level stops those messages causing dumps.
+    pub fn set_store_level(&self, level: log_common::Level) {
+        self.log_level.store(level as u8, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// Check if a message of `level` would be sent, counting it as filtered if not
+    /// 
+    /// `Off` is not a message level, so is never sent.
+    fn is_enabled(&self, level: log_common::Level) -> bool {
+        let ans = log_common::Level::Off != level
+            && (level as u8) <= self.log_level.load(std::sync::atomic::Ordering::Relaxed);
+        if !ans {
+            self.counters.count_filtered();
+        }
+        ans
     }
 
     /// Construct and send massage if valid `level`
+    /// 
+    /// Records the location of the caller into the message.
+    #[track_caller]
     fn construct_and_send(&self, level: log_common::Level, line: String) {
-        if level <= self.log_level {
-            let d = log_common::LogData::new(level, log_common::get_time_now(), line);
+        self.construct_and_send_fields(level, line, vec!());
+    }
+
+    /// Construct and send massage with custom fields if valid `level`
+    /// 
+    /// A message that can not be sent is handled according to the `SendFailurePolicy`.
+    #[track_caller]
+    fn construct_and_send_fields(&self, level: log_common::Level, line: String, fields: std::vec::Vec<(String, String)>) {
+        if let Err(d) = self.try_construct_and_send_fields(level, line, fields) {
+            self.handle_send_failure(d);
+        }
+    }
+
+    /// Construct and send massage with custom fields if valid `level`
+    /// 
+    /// Returns the message if the receiver is gone.
+    #[track_caller]
+    fn try_construct_and_send_fields(
+        &self,
+        level: log_common::Level,
+        line: String,
+        fields: std::vec::Vec<(String, String)>,
+    ) -> Result<(), log_common::LogData> {
+        if self.is_enabled(level) {
+            let mut d = log_common::LogData::new(level, log_common::get_time_now(), line)
+                .with_location(log_common::SourceLocation::caller())
+                .with_fields(fields);
+            if let Some(t) = log_common::get_thread_name() {
+                d = d.with_thread(t);
+            }
+            self.try_send(d)
+        } else {
+            Ok(()) // Drop message
+        }
+    }
+
+    /// Send message built elsewhere, such as received from another process, if valid level
+    pub(crate) fn forward(&self, d: log_common::LogData) {
+        if self.is_enabled(d.level()) {
+            if let Err(d) = self.try_send(d) {
+                self.handle_send_failure(d);
+            }
+        }
+    }
+
+    /// Send message, returning it if the receiver is gone
+    ///
+    /// Records dropped by a full channel are counted by the receiver, not as sent.
+    fn try_send(&self, d: log_common::LogData) -> Result<(), log_common::LogData> {
+        match self.sender.send(log_channel::Message::Record(d)) {
+            Ok(log_channel::Sent::Queued) => self.counters.count_sent(),
+            Ok(log_channel::Sent::Dropped) => (),
+            // On failure the channel gives back the record that was sent
+            Err(log_channel::SendError(log_channel::Message::Record(d))) => return Err(d),
+            Err(_) => (),
+        }
+        Ok(())
+    }
+
+    /// Apply `SendFailurePolicy` to a message that could not be sent
+    fn handle_send_failure(&self, d: log_common::LogData) {
+        match self.send_failure_policy {
+            SendFailurePolicy::Drop => (),
+            SendFailurePolicy::Stderr => {
+                use std::io::Write;
+                // Ignore failure, nowhere left to report it
+                let _ret = std::io::stderr().write_all(d.as_string().as_bytes());
+            }
+        }
+    }
+
+    /// Change the dump level of the receiver
+    /// 
+    /// Messages this severe and more severe will cause the buffer to be dumped.
+    /// `Off` disables dumps.
+    /// Applies to messages sent after this call.
+    pub fn set_dump_level(&self, level: log_common::Level) {
+        self.send_command(log_channel::Reconfigure::SetDumpLevel(level));
+    }
 
-            let _ret = self.sender.send(d);
-            debug_assert!(_ret.is_ok());
-        } // else, drop message
+    /// Stop dumping the buffer until [`LogSender::resume_dumps`] is called
+    /// 
+    /// Messages keep being buffered.
+    /// The buffer is still dumped if every `LogSender` is dropped without `shutdown()`.
+    pub fn pause_dumps(&self) {
+        self.send_command(log_channel::Reconfigure::PauseDumps);
+    }
+
+    /// Resume dumping after [`LogSender::pause_dumps`]
+    pub fn resume_dumps(&self) {
+        self.send_command(log_channel::Reconfigure::ResumeDumps);
+    }
+
+    /// Replace the policy deciding when a message at or above the dump level causes a dump
+    pub fn set_trigger_policy(&self, policy: log_receiver::TriggerPolicy) {
+        self.send_command(log_channel::Reconfigure::SetTriggerPolicy(policy));
+    }
+
+    /// Dump the buffer now, without logging an error
+    /// 
+    /// For when something is known to be wrong, such as a failed health check,
+    /// and the history leading up to it should be kept.
+    /// `reason` is written in the header of the dump.
+    /// Dumps even if dumps are paused or the trigger policy would not dump.
+    pub fn dump_now(&self, reason: &str) {
+        self.send_command_message(log_channel::Message::Dump(reason.to_string()));
+    }
+
+    /// Block until every message previously sent from this thread has been handled by the receiver
+    /// 
+    /// With `LoggerBuilder::per_thread_rings` messages sent from other threads
+    /// at about the same time may still be pending.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns an error if the receiver is not running, or does not finish within a few seconds.
+    pub fn flush(&self) -> Result<(), &'static str> {
+        let (ack, acked) = std::sync::mpsc::channel();
+        if self.sender.send(log_channel::Message::Flush(ack)).is_err() {
+            return Err("Receiver is not running");
+        }
+        match acked.recv_timeout(QUERY_TIMEOUT) {
+            Ok(()) => Ok(()),
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => Err("Receiver did not complete flush in time"),
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => Err("Receiver stopped before flush completed"),
+        }
+    }
+
+    /// Ask the receiver for its current dump level
+    /// 
+    /// Returns `None` if the receiver is not running or does not answer within a few seconds.
+    pub fn dump_level(&self) -> Option<log_common::Level> {
+        let (reply, answer) = std::sync::mpsc::channel();
+        if self.sender.send(log_channel::Message::Query(log_channel::Query::DumpLevel(reply))).is_ok() {
+            answer.recv_timeout(QUERY_TIMEOUT).ok()
+        } else {
+            None
+        }
+    }
+
+    /// Counters about the logger itself
+    /// 
+    /// Records sent and filtered by the senders, dropped by a full channel, received,
+    /// lines evicted from buffers before being written, dumps, sink failures,
+    /// bytes written, the longest time a record waited in the channel, and the last error.
+    /// 
+    /// If the receiver is not running or does not answer within a few seconds,
+    /// only the sender counters are filled in and `last_error` says so.
+    pub fn stats(&self) -> log_stats::Stats {
+        let (reply, answer) = std::sync::mpsc::channel();
+        let ans = if self.sender.send(log_channel::Message::Query(log_channel::Query::Stats(reply))).is_ok() {
+            answer.recv_timeout(QUERY_TIMEOUT).ok()
+        } else {
+            None
+        };
+        ans.unwrap_or_else(|| log_stats::Stats {
+            last_error: Some("Receiver is not running".to_string()),
+            ..self.counters.stats()
+        })
+    }
+
+    /// Copy of the lines currently buffered by each sink, oldest first
+    /// 
+    /// Unlike a dump, the buffers are left untouched,
+    /// for showing recent activity such as on a diagnostics endpoint.
+    /// Streaming sinks have no buffer so are not included.
+    /// 
+    /// Returns `None` if the receiver is not running or does not answer within a few seconds.
+    pub fn snapshot(&self) -> Option<std::vec::Vec<log_receiver::sink::SinkSnapshot>> {
+        let (reply, answer) = std::sync::mpsc::channel();
+        if self.sender.send(log_channel::Message::Query(log_channel::Query::Snapshot(reply))).is_ok() {
+            answer.recv_timeout(QUERY_TIMEOUT).ok()
+        } else {
+            None
+        }
+    }
+
+    /// Send reconfigure command to receiver
+    fn send_command(&self, command: log_channel::Reconfigure) {
+        self.send_command_message(log_channel::Message::Reconfigure(command));
+    }
+
+    /// Send command to receiver
+    /// 
+    /// Commands sent after the receiver is gone have nothing to act on, so are dropped.
+    fn send_command_message(&self, message: log_channel::Message) {
+        let _ret = self.sender.send(message);
     }
 
     /// Close logging thread
     /// 
-    /// Sends command to set log level to `Off`,
+    /// Sends command to shutdown,
     /// which signals thread to exit.
     /// code waits for mpsc to disconnect before returning.
     /// 
@@ -38,19 +293,12 @@ impl LogSender {
     pub fn shutdown(&self) {
         const DELAY: std::time::Duration = std::time::Duration::from_millis(100);
 
-        let off_command = log_common::LogData::new(
-            log_common::Level::Off,
-            log_common::get_time_now(),
-            String::new(),
-        );
-
         // Wait up to 500 * DELAY for MPSC connection to drop signaling the reciever has closed.
         for _s in 0..500 {
             // Per: https://doc.rust-lang.org/std/sync/mpsc/struct.SendError.html
             // A send operation can only fail if the receiving end of a channel is disconnected,
             // implying that the data could never be received.
-            // The error contains the data being sent as a payload so it can be recovered.
-            if self.sender.send(off_command.clone()).is_ok() {
+            if self.sender.send(log_channel::Message::Shutdown).is_ok() {
                 std::thread::sleep(DELAY);
             } else {
                 // Connection has dropped. Stop waiting.
@@ -64,11 +312,12 @@ impl LogSender {
     /// Message will only be sent to buffer if 
     /// log threshold configured to allow this level.
     ///
-    /// # Panic
+    /// # Send failure
     /// 
-    /// Will panic if built in debug mode and MSPC send fails
+    /// If the receiver is gone the message is handled according to the `SendFailurePolicy`.
     ///
     #[allow(dead_code)]
+    #[track_caller]
     pub fn trace_str(&self, line: &str) {
         self.construct_and_send(log_common::Level::Trace, line.to_string());
     }
@@ -79,11 +328,12 @@ impl LogSender {
     /// Message will only be sent to buffer if 
     /// log threshold configured to allow this level.
     ///
-    /// # Panic
+    /// # Send failure
     /// 
-    /// Will panic if built in debug mode and MSPC send fails
+    /// If the receiver is gone the message is handled according to the `SendFailurePolicy`.
     ///
     #[allow(dead_code)]
+    #[track_caller]
     pub fn trace_string(&self, line: String) {
         self.construct_and_send(log_common::Level::Trace, line);
     }
@@ -94,11 +344,12 @@ impl LogSender {
     /// Message will only be sent to buffer if 
     /// log threshold configured to allow this level.
     ///
-    /// # Panic
+    /// # Send failure
     /// 
-    /// Will panic if built in debug mode and MSPC send fails
+    /// If the receiver is gone the message is handled according to the `SendFailurePolicy`.
     ///
     #[allow(dead_code)]
+    #[track_caller]
     pub fn info_str(&self, line: &str) {
         self.construct_and_send(log_common::Level::Info, line.to_string());
     }
@@ -108,11 +359,12 @@ impl LogSender {
     /// Message will only be sent to buffer if 
     /// log threshold configured to allow this level.
     ///
-    /// # Panic
+    /// # Send failure
     /// 
-    /// Will panic if built in debug mode and MSPC send fails
+    /// If the receiver is gone the message is handled according to the `SendFailurePolicy`.
     ///
     #[allow(dead_code)]
+    #[track_caller]
     pub fn info_string(&self, line: String) {
         self.construct_and_send(log_common::Level::Info, line);
     }
@@ -123,11 +375,12 @@ impl LogSender {
     /// Message will only be sent to buffer if 
     /// log threshold configured to allow this level.
     ///
-    /// # Panic
+    /// # Send failure
     /// 
-    /// Will panic if built in debug mode and MSPC send fails
+    /// If the receiver is gone the message is handled according to the `SendFailurePolicy`.
     ///
     #[allow(dead_code)]
+    #[track_caller]
     pub fn warn_str(&self, line: &str) {
         self.construct_and_send(log_common::Level::Warn, line.to_string());
     }
@@ -137,11 +390,12 @@ impl LogSender {
     /// Message will only be sent to buffer if 
     /// log threshold configured to allow this level.
     ///
-    /// # Panic
+    /// # Send failure
     /// 
-    /// Will panic if built in debug mode and MSPC send fails
+    /// If the receiver is gone the message is handled according to the `SendFailurePolicy`.
     ///
     #[allow(dead_code)]
+    #[track_caller]
     pub fn warn_string(&self, line: String) {
         self.construct_and_send(log_common::Level::Warn, line);
     }
@@ -151,11 +405,12 @@ impl LogSender {
     /// Message will only be sent to buffer if 
     /// log threshold configured to allow this level.
     ///
-    /// # Panic
+    /// # Send failure
     /// 
-    /// Will panic if built in debug mode and MSPC send fails
+    /// If the receiver is gone the message is handled according to the `SendFailurePolicy`.
     ///
     #[allow(dead_code)]
+    #[track_caller]
     pub fn error_str(&self, line: &str) {
         self.construct_and_send(log_common::Level::Error, line.to_string());
     }
@@ -165,13 +420,49 @@ impl LogSender {
     /// Message will only be sent to buffer if 
     /// log threshold configured to allow this level.
     ///
-    /// # Panic
+    /// # Send failure
     /// 
-    /// Will panic if built in debug mode and MSPC send fails
+    /// If the receiver is gone the message is handled according to the `SendFailurePolicy`.
     ///
     #[allow(dead_code)]
+    #[track_caller]
     pub fn error_string(&self, line: String) {
         self.construct_and_send(log_common::Level::Error, line);
     }
 
+    /// Log a message with custom key value fields
+    ///
+    /// Fields are kept alongside the message for sinks that support them,
+    /// such as journald.
+    /// Message will only be sent to buffer if 
+    /// log threshold configured to allow this level.
+    ///
+    /// # Send failure
+    /// 
+    /// If the receiver is gone the message is handled according to the `SendFailurePolicy`.
+    ///
+    #[allow(dead_code)]
+    #[track_caller]
+    pub fn log_fields(&self, level: log_common::Level, line: &str, fields: &[(&str, &str)]) {
+        if self.is_enabled(level) {
+            let fields = fields.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+            self.construct_and_send_fields(level, line.to_string(), fields);
+        }
+    }
+
+    /// Log a message, reporting if it could not be sent
+    /// 
+    /// Unlike the other log methods, the `SendFailurePolicy` is not applied,
+    /// the caller decides what to do.
+    /// A message less severe than the store level is dropped and is not an error.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns an error if the receiver has shut down or died.
+    #[track_caller]
+    pub fn try_log(&self, level: log_common::Level, line: &str) -> Result<(), &'static str> {
+        self.try_construct_and_send_fields(level, line.to_string(), vec!())
+            .map_err(|_| "Receiver is not running")
+    }
+
 }
@@ -0,0 +1,81 @@
+#![cfg(test)]
+
+use super::*;
+
+
+#[test]
+fn test_sender_counters() {
+    let counters = SenderCounters::default();
+    counters.count_sent();
+    counters.count_sent();
+    counters.count_filtered();
+
+    let stats = counters.stats();
+    assert!(2 == stats.sent);
+    assert!(1 == stats.filtered);
+    assert!(0 == stats.received);
+}
+
+
+#[test]
+fn test_display() {
+    let mut stats = Stats {
+        sent: 5,
+        dumps: 2,
+        max_channel_lag: std::time::Duration::from_millis(12),
+        ..Stats::default()
+    };
+    assert!(
+        "sent=5 filtered=0 dropped=0 received=0 evicted=0 undelivered=0 dumps=2 dump_failures=0 stream_failures=0 bytes_written=0 max_channel_lag_ms=12 restarts=0 last_error=none"
+            == stats.to_string()
+    );
+
+    stats.last_error = Some("Sink 'x' failed".to_string());
+    assert!(stats.to_string().ends_with("last_error=Sink 'x' failed"));
+}
+
+
+#[test]
+fn test_prometheus() {
+    let stats = Stats {
+        sent: 10,
+        filtered: 3,
+        dropped: 2,
+        received: 8,
+        evicted: 1,
+        undelivered: 5,
+        dumps: 4,
+        dump_failures: 1,
+        stream_failures: 3,
+        bytes_written: 1234,
+        max_channel_lag: std::time::Duration::from_millis(250),
+        restarts: 1,
+        last_error: Some("Sink 'x' failed".to_string()),
+    };
+    let text = stats.to_prometheus();
+    let lines: std::vec::Vec<&str> = text.lines().filter(|l| !l.starts_with('#')).collect();
+    let expected = [
+        "pragmatic_logger_records_sent_total 10",
+        "pragmatic_logger_records_received_total 8",
+        "pragmatic_logger_records_dropped_total{reason=\"filtered\"} 3",
+        "pragmatic_logger_records_dropped_total{reason=\"channel_full\"} 2",
+        "pragmatic_logger_records_dropped_total{reason=\"evicted\"} 1",
+        "pragmatic_logger_records_dropped_total{reason=\"undelivered\"} 5",
+        "pragmatic_logger_dumps_total 4",
+        "pragmatic_logger_dump_failures_total 1",
+        "pragmatic_logger_stream_failures_total 3",
+        "pragmatic_logger_bytes_written_total 1234",
+        "pragmatic_logger_max_channel_lag_seconds 0.25",
+        "pragmatic_logger_receiver_restarts_total 1",
+    ];
+    assert!(expected.len() == lines.len());
+    for (e, l) in expected.iter().zip(lines.iter()) {
+        assert!(e == l);
+    }
+
+    // Every metric has help and type
+    assert!(text.contains("# TYPE pragmatic_logger_dumps_total counter\n"));
+    assert!(text.contains("# TYPE pragmatic_logger_max_channel_lag_seconds gauge\n"));
+    assert!(9 == text.lines().filter(|l| l.starts_with("# HELP")).count());
+    assert!(text.ends_with('\n'));
+}
@@ -0,0 +1,150 @@
+// log stats module
+//
+// Counters describing the logger itself.
+// Senders count into shared atomics, the receiver thread keeps its own counters
+// and combines both when asked.
+
+#[cfg(test)]
+mod log_stats_tests;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Counters about the logger, see [`crate::log_sender::LogSender::stats`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    /// Records handed to the channel by senders
+    pub sent: u64,
+    /// Records dropped at the sender because they were less severe than the store level
+    pub filtered: u64,
+    /// Records dropped because the channel was full
+    pub dropped: u64,
+    /// Records taken off the channel by the receiver
+    pub received: u64,
+    /// Buffered lines overwritten before they were written, summed over sinks
+    pub evicted: u64,
+    /// Lines a sink's writer accepted and then discarded, such as when a network outbox is full
+    pub undelivered: u64,
+    /// Dumps performed
+    pub dumps: u64,
+    /// Sink writes that failed during a dump
+    pub dump_failures: u64,
+    /// Writes to streaming sinks that failed
+    pub stream_failures: u64,
+    /// Bytes written by all sinks
+    pub bytes_written: u64,
+    /// Longest time between a record being created and received
+    pub max_channel_lag: std::time::Duration,
+    /// Times the receiver was restarted after a panic
+    pub restarts: u64,
+    /// Most recent failure
+    pub last_error: Option<String>,
+}
+
+impl std::fmt::Display for Stats {
+    /// Single line of `key=value` pairs
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "sent={} filtered={} dropped={} received={} evicted={} undelivered={} dumps={} dump_failures={} stream_failures={} bytes_written={} max_channel_lag_ms={} restarts={} last_error={}",
+            self.sent,
+            self.filtered,
+            self.dropped,
+            self.received,
+            self.evicted,
+            self.undelivered,
+            self.dumps,
+            self.dump_failures,
+            self.stream_failures,
+            self.bytes_written,
+            self.max_channel_lag.as_millis(),
+            self.restarts,
+            self.last_error.as_deref().unwrap_or("none"),
+        )
+    }
+}
+
+impl Stats {
+    /// Render as Prometheus text exposition format
+    /// 
+    /// For serving from an existing metrics endpoint.
+    /// Dropped records are one counter with a `reason` label of
+    /// `filtered`, `channel_full`, `evicted` or `undelivered`.
+    /// `last_error` is not a number so is not included.
+    pub fn to_prometheus(&self) -> String {
+        let mut ans = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: &[(&str, String)]| {
+            ans += &format!("# HELP pragmatic_logger_{} {}\n", name, help);
+            ans += &format!("# TYPE pragmatic_logger_{} {}\n", name, kind);
+            for (labels, value) in samples {
+                ans += &format!("pragmatic_logger_{}{} {}\n", name, labels, value);
+            }
+        };
+
+        metric("records_sent_total", "counter", "Records handed to the channel by senders.",
+            &[("", self.sent.to_string())]);
+        metric("records_received_total", "counter", "Records taken off the channel by the receiver.",
+            &[("", self.received.to_string())]);
+        metric("records_dropped_total", "counter", "Records lost before being written.", &[
+            ("{reason=\"filtered\"}", self.filtered.to_string()),
+            ("{reason=\"channel_full\"}", self.dropped.to_string()),
+            ("{reason=\"evicted\"}", self.evicted.to_string()),
+            ("{reason=\"undelivered\"}", self.undelivered.to_string()),
+        ]);
+        metric("dumps_total", "counter", "Dumps performed.",
+            &[("", self.dumps.to_string())]);
+        metric("dump_failures_total", "counter", "Sink writes that failed during a dump.",
+            &[("", self.dump_failures.to_string())]);
+        metric("stream_failures_total", "counter", "Writes to streaming sinks that failed.",
+            &[("", self.stream_failures.to_string())]);
+        metric("bytes_written_total", "counter", "Bytes written by all sinks.",
+            &[("", self.bytes_written.to_string())]);
+        metric("max_channel_lag_seconds", "gauge", "Longest time between a record being created and received.",
+            &[("", self.max_channel_lag.as_secs_f64().to_string())]);
+        metric("receiver_restarts_total", "counter", "Times the receiver was restarted after a panic.",
+            &[("", self.restarts.to_string())]);
+        ans
+    }
+
+    /// Take the counters kept by the receiver from `receiver`
+    pub(crate) fn merge_receiver(self, receiver: &Stats) -> Self {
+        Self {
+            dropped: receiver.dropped,
+            received: receiver.received,
+            dumps: receiver.dumps,
+            dump_failures: receiver.dump_failures,
+            stream_failures: receiver.stream_failures,
+            max_channel_lag: receiver.max_channel_lag,
+            restarts: receiver.restarts,
+            last_error: receiver.last_error.clone(),
+            ..self
+        }
+    }
+}
+
+
+
+/// Counters shared by every clone of a sender, and read by the receiver
+#[derive(Debug, Default)]
+pub struct SenderCounters {
+    sent: AtomicU64,
+    filtered: AtomicU64,
+}
+
+impl SenderCounters {
+    pub fn count_sent(&self) {
+        self.sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn count_filtered(&self) {
+        self.filtered.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Stats with only the sender counters filled in
+    pub fn stats(&self) -> Stats {
+        Stats {
+            sent: self.sent.load(Ordering::Relaxed),
+            filtered: self.filtered.load(Ordering::Relaxed),
+            ..Stats::default()
+        }
+    }
+}
@@ -0,0 +1,141 @@
+#![cfg(test)]
+
+use super::*;
+
+fn no_env(_name: &str) -> Option<String> {
+    None
+}
+
+fn error(contents: &str) -> ConfigError {
+    load(contents, no_env).err().unwrap()
+}
+
+
+#[test]
+fn test_load() {
+    // "TEST_DIRECTORY" assumed to be defined in [env] of `.cargo/config.toml`
+    let rw_dir = std::env::var("TEST_DIRECTORY").unwrap();
+    let fp = std::path::PathBuf::from(&rw_dir).join("test_config_load.txt");
+    let fp_json = std::path::PathBuf::from(&rw_dir).join("test_config_load.json");
+
+    let contents = format!(r#"
+        store_level = "Trace"
+        dump_level = "error"
+        buffer_size = 64
+        path = "{}"
+        stats_in_dump_header = true
+        on_send_failure = "stderr"
+
+        [channel]
+        kind = "bounded"
+        capacity = 100
+        overflow = "drop_oldest_below"
+        overflow_level = "Warn"
+
+        [trigger]
+        policy = "burst"
+        count = 3
+        window_ms = 500
+
+        [[sinks]]
+        kind = "file"
+        path = "{}"
+        format = "json"
+        min_level = "Info"
+
+        [[sinks]]
+        kind = "stderr"
+        min_level = "Warn"
+        streaming = true
+    "#, fp.to_str().unwrap(), fp_json.to_str().unwrap());
+
+    let builder = load(&contents, no_env).unwrap();
+    assert!(log_common::Level::Trace == builder.store_log_level);
+    assert!(log_common::Level::Error == builder.dump_log_level);
+    assert!(64 == builder.buffer_size.value());
+    assert!(3 == builder.sinks.len());
+    assert!(builder.is_stats_in_header);
+    assert!(matches!(builder.send_failure_policy, log_sender::SendFailurePolicy::Stderr));
+    assert!(matches!(builder.channel,
+        ChannelConfig::Bounded(100, log_channel::OverflowPolicy::DropOldestBelow(log_common::Level::Warn))));
+    assert!(matches!(builder.trigger_policy, log_receiver::TriggerPolicy::Burst { count: 3, window }
+        if std::time::Duration::from_millis(500) == window));
+
+    let log = builder.build().unwrap();
+    log.shutdown();
+}
+
+#[test]
+fn test_env_override() {
+    let contents = r#"
+        store_level = "Info"
+        dump_level = "Error"
+        buffer_size = 64
+        [[sinks]]
+        kind = "stderr"
+    "#;
+    let env = |name: &str| match name {
+        "PRAGMATIC_LOGGER_DUMP_LEVEL" => Some("Warn".to_string()),
+        "PRAGMATIC_LOGGER_BUFFER_SIZE" => Some("1024".to_string()),
+        _ => None,
+    };
+    let builder = load(contents, env).unwrap();
+    assert!(log_common::Level::Info == builder.store_log_level);
+    assert!(log_common::Level::Warn == builder.dump_log_level);
+    assert!(1024 == builder.buffer_size.value());
+
+    let env = |name: &str| match name {
+        "PRAGMATIC_LOGGER_BUFFER_SIZE" => Some("big".to_string()),
+        _ => None,
+    };
+    let e = load(contents, env).err().unwrap();
+    assert!("PRAGMATIC_LOGGER_BUFFER_SIZE" == e.key);
+
+    let env = |name: &str| match name {
+        "PRAGMATIC_LOGGER_STORE_LEVEL" => Some("Loud".to_string()),
+        _ => None,
+    };
+    let e = load(contents, env).err().unwrap();
+    assert!("PRAGMATIC_LOGGER_STORE_LEVEL" == e.key);
+    assert!(e.to_string().starts_with("Invalid `PRAGMATIC_LOGGER_STORE_LEVEL`: expected one of"));
+
+    let env = |name: &str| match name {
+        "PRAGMATIC_LOGGER_ON_SEND_FAILURE" => Some("panic".to_string()),
+        _ => None,
+    };
+    assert!("PRAGMATIC_LOGGER_ON_SEND_FAILURE" == load(contents, env).err().unwrap().key);
+
+    let env = |name: &str| match name {
+        "PRAGMATIC_LOGGER_BUFFER_SIZE" => Some("100".to_string()),
+        _ => None,
+    };
+    assert!("PRAGMATIC_LOGGER_BUFFER_SIZE" == load(contents, env).err().unwrap().key);
+}
+
+#[test]
+fn test_errors() {
+    const BASE: &str = "store_level = \"Trace\"\ndump_level = \"Error\"\nbuffer_size = 64\n";
+
+    let e = error("store_level = ");
+    assert!(e.key.is_empty());
+    assert!(e.to_string().starts_with("Config is not valid TOML"));
+
+    let e = error("dump_level = \"Error\"\nbuffer_size = 64\n");
+    assert!("store_level" == e.key);
+    assert!("Invalid `store_level`: required key is missing" == e.to_string());
+
+    assert!("dump_level" == error("store_level = \"Warn\"\ndump_level = \"Info\"\nbuffer_size = 64\n").key);
+    assert!("buffer_size" == error("store_level = \"Trace\"\ndump_level = \"Error\"\nbuffer_size = 100\n").key);
+    assert!("sinks" == error(BASE).key);
+    assert!("dump_levle" == error(&format!("{}dump_levle = \"Warn\"\n", BASE)).key);
+    assert!("channel.capacity" == error(&format!("{}[channel]\nkind = \"bounded\"\ncapacity = 0\n", BASE)).key);
+    assert!("channel.capacity" == error(&format!("{}[channel]\nkind = \"bounded\"\ncapacity = 1000000000\n", BASE)).key);
+    assert!("trigger.count" == error(&format!("{}[trigger]\npolicy = \"burst\"\ncount = 0\nwindow_ms = 100\n", BASE)).key);
+    assert!("trigger.cooldown_ms" == error(&format!("{}[trigger]\npolicy = \"cooldown\"\n", BASE)).key);
+    assert!("sinks[1].format" == error(&format!(
+        "{}[[sinks]]\nkind = \"stderr\"\n[[sinks]]\nkind = \"stderr\"\nformat = \"xml\"\n", BASE)).key);
+    assert!("sinks[0].kind" == error(&format!("{}[[sinks]]\nkind = \"carrier pigeon\"\n", BASE)).key);
+
+    let e = LoggerBuilder::from_config_file("missing.toml").err().unwrap();
+    assert!(e.key.is_empty());
+}
@@ -0,0 +1,405 @@
+// config module
+//
+// Builds a `LoggerBuilder` from a TOML file, with environment variables overriding top level keys.
+// Every value is checked while reading, so errors name the key at fault,
+// and a builder returned from here passes the checks in `LoggerBuilder::build`.
+
+use super::{ChannelConfig, LoggerBuilder};
+use super::log_common;
+use super::log_channel;
+use super::log_receiver;
+use super::log_sender;
+
+#[cfg(test)]
+mod config_tests;
+
+/// Prefix of environment variables overriding top level keys, such as `PRAGMATIC_LOGGER_DUMP_LEVEL`
+pub const ENV_PREFIX: &str = "PRAGMATIC_LOGGER_";
+
+/// Top level keys that can be overridden from the environment
+const ENV_KEYS: [&str; 7] = [
+    "store_level",
+    "dump_level",
+    "buffer_size",
+    "path",
+    "run_marker",
+    "stats_in_dump_header",
+    "on_send_failure",
+];
+
+/// Error loading a configuration, with the key it was found at
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigError {
+    /// Key such as `"sinks[1].format"`, the environment variable name for an override,
+    /// or empty if the file could not be read or parsed
+    pub key: String,
+    pub message: String,
+}
+
+impl ConfigError {
+    fn new(key: &str, message: &str) -> Self {
+        Self { key: key.to_string(), message: message.to_string() }
+    }
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.key.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "Invalid `{}`: {}", self.key, self.message)
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<ConfigError> for String {
+    fn from(e: ConfigError) -> Self {
+        e.to_string()
+    }
+}
+
+
+/// Read TOML file at `path`, with overrides from the process environment
+pub(super) fn load_file(path: &str) -> Result<LoggerBuilder, ConfigError> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|_| ConfigError::new("", &format!("Failed to read config file '{}'", path)))?;
+    load(&contents, |name| std::env::var(name).ok())
+}
+
+/// Read TOML `contents`, with overrides looked up by environment variable name in `env`
+pub(super) fn load(contents: &str, env: impl Fn(&str) -> Option<String>) -> Result<LoggerBuilder, ConfigError> {
+    let mut table: toml::Table = contents
+        .parse()
+        .map_err(|e: toml::de::Error| ConfigError::new("", &format!("Config is not valid TOML: {}", e.message())))?;
+    let overridden = apply_env(&mut table, env)?;
+
+    // Errors in a value taken from the environment name the variable it came from
+    from_table(&table).map_err(|e| match overridden.iter().find(|(key, _)| *key == e.key) {
+        Some((_, name)) => ConfigError::new(name, &e.message),
+        None => e,
+    })
+}
+
+fn from_table(table: &toml::Table) -> Result<LoggerBuilder, ConfigError> {
+    let t = Table { table, prefix: String::new() };
+    t.check_keys(&[
+        "store_level",
+        "dump_level",
+        "buffer_size",
+        "path",
+        "run_marker",
+        "stats_in_dump_header",
+        "on_send_failure",
+        "channel",
+        "trigger",
+        "sinks",
+    ])?;
+
+    let store_level = t.required("store_level", parse_level)?;
+    let dump_level = t.required("dump_level", parse_level)?;
+    if store_level < dump_level {
+        return Err(t.error("dump_level", "must not be less severe than store_level"));
+    }
+    let buffer_size = t.required("buffer_size", parse_buffer_size)?;
+
+    let mut builder = LoggerBuilder::new(store_level, dump_level, buffer_size);
+    if let Some(path) = t.optional("path", as_str)? {
+        builder = builder.add_sink(log_receiver::Sink::file(path).map_err(|e| t.error("path", e))?);
+    }
+    if let Some(path) = t.optional("run_marker", as_str)? {
+        builder = builder.run_marker(path);
+    }
+    if let Some(true) = t.optional("stats_in_dump_header", as_bool)? {
+        builder = builder.stats_in_dump_header();
+    }
+    if let Some(policy) = t.optional("on_send_failure", parse_send_failure_policy)? {
+        builder = builder.on_send_failure(policy);
+    }
+    if let Some(channel) = t.optional_table("channel")? {
+        builder.channel = parse_channel(&channel)?;
+    }
+    if let Some(trigger) = t.optional_table("trigger")? {
+        builder = builder.trigger_policy(parse_trigger(&trigger)?);
+    }
+    if let Some(sinks) = t.table.get("sinks") {
+        let sinks = sinks.as_array().ok_or_else(|| t.error("sinks", "expected an array of tables, [[sinks]]"))?;
+        for (k, sink) in sinks.iter().enumerate() {
+            let key = format!("sinks[{}]", k);
+            let sink = sink.as_table().ok_or_else(|| ConfigError::new(&key, "expected a table"))?;
+            builder = builder.add_sink(parse_sink(&Table { table: sink, prefix: format!("{}.", key) })?);
+        }
+    }
+
+    if builder.sinks.is_empty() {
+        return Err(ConfigError::new("sinks", "at least one sink, or `path`, is required"));
+    }
+    Ok(builder)
+}
+
+
+/// Replace top level keys with environment variables that are set
+///
+/// Values are read as the type the key expects, so errors name the variable.
+/// Returns the keys replaced, with the name of the variable each came from.
+fn apply_env(
+    table: &mut toml::Table,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<std::vec::Vec<(&'static str, String)>, ConfigError> {
+    let mut overridden = std::vec::Vec::new();
+    for key in ENV_KEYS {
+        let name = format!("{}{}", ENV_PREFIX, key.to_ascii_uppercase());
+        if let Some(v) = env(&name) {
+            let value = match key {
+                "buffer_size" => toml::Value::Integer(v.trim().parse().map_err(|_| ConfigError::new(&name, "expected an integer"))?),
+                "stats_in_dump_header" => toml::Value::Boolean(v.trim().parse().map_err(|_| ConfigError::new(&name, "expected true or false"))?),
+                _ => toml::Value::String(v),
+            };
+            table.insert(key.to_string(), value);
+            overridden.push((key, name));
+        }
+    }
+    Ok(overridden)
+}
+
+/// Table along with the path of keys leading to it, for error messages
+struct Table<'a> {
+    table: &'a toml::Table,
+    prefix: String,
+}
+
+impl<'a> Table<'a> {
+    fn error(&self, key: &str, message: &str) -> ConfigError {
+        ConfigError::new(&format!("{}{}", self.prefix, key), message)
+    }
+
+    /// Reject keys not in `known`, catching misspellings that would otherwise be silently ignored
+    fn check_keys(&self, known: &[&str]) -> Result<(), ConfigError> {
+        match self.table.keys().find(|k| !known.contains(&k.as_str())) {
+            Some(k) => Err(self.error(k, "unknown key")),
+            None => Ok(()),
+        }
+    }
+
+    fn optional<T>(
+        &self,
+        key: &str,
+        parse: impl Fn(&'a toml::Value) -> Result<T, &'static str>,
+    ) -> Result<Option<T>, ConfigError> {
+        match self.table.get(key) {
+            Some(v) => parse(v).map(Some).map_err(|e| self.error(key, e)),
+            None => Ok(None),
+        }
+    }
+
+    fn required<T>(
+        &self,
+        key: &str,
+        parse: impl Fn(&'a toml::Value) -> Result<T, &'static str>,
+    ) -> Result<T, ConfigError> {
+        self.optional(key, parse)?.ok_or_else(|| self.error(key, "required key is missing"))
+    }
+
+    fn optional_table(&self, key: &str) -> Result<Option<Table<'a>>, ConfigError> {
+        match self.table.get(key) {
+            Some(v) => match v.as_table() {
+                Some(table) => Ok(Some(Table { table, prefix: format!("{}{}.", self.prefix, key) })),
+                None => Err(self.error(key, "expected a table")),
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+fn as_str(v: &toml::Value) -> Result<&str, &'static str> {
+    v.as_str().ok_or("expected a string")
+}
+
+fn as_bool(v: &toml::Value) -> Result<bool, &'static str> {
+    v.as_bool().ok_or("expected true or false")
+}
+
+fn as_usize(v: &toml::Value) -> Result<usize, &'static str> {
+    v.as_integer()
+        .and_then(|i| usize::try_from(i).ok())
+        .ok_or("expected a non negative integer")
+}
+
+fn as_duration_ms(v: &toml::Value) -> Result<std::time::Duration, &'static str> {
+    as_usize(v).map(|ms| std::time::Duration::from_millis(ms as u64))
+}
+
+fn parse_level(v: &toml::Value) -> Result<log_common::Level, &'static str> {
+    const EXPECTED: &str = "expected one of Trace, Info, Warn, Error, Off";
+    match v.as_str().ok_or(EXPECTED)?.to_ascii_lowercase().as_str() {
+        "trace" => Ok(log_common::Level::Trace),
+        "info" => Ok(log_common::Level::Info),
+        "warn" => Ok(log_common::Level::Warn),
+        "error" => Ok(log_common::Level::Error),
+        "off" => Ok(log_common::Level::Off),
+        _ => Err(EXPECTED),
+    }
+}
+
+fn parse_buffer_size(v: &toml::Value) -> Result<log_receiver::BufferSize, &'static str> {
+    const EXPECTED: &str = "expected one of 16, 32, 64, 128, 256, 512, 1024, 2048";
+    match v.as_integer().ok_or(EXPECTED)? {
+        16 => Ok(log_receiver::BufferSize::Size16),
+        32 => Ok(log_receiver::BufferSize::Size32),
+        64 => Ok(log_receiver::BufferSize::Size64),
+        128 => Ok(log_receiver::BufferSize::Size128),
+        256 => Ok(log_receiver::BufferSize::Size256),
+        512 => Ok(log_receiver::BufferSize::Size512),
+        1024 => Ok(log_receiver::BufferSize::Size1024),
+        2048 => Ok(log_receiver::BufferSize::Size2048),
+        _ => Err(EXPECTED),
+    }
+}
+
+fn parse_send_failure_policy(v: &toml::Value) -> Result<log_sender::SendFailurePolicy, &'static str> {
+    match as_str(v)? {
+        "drop" => Ok(log_sender::SendFailurePolicy::Drop),
+        "stderr" => Ok(log_sender::SendFailurePolicy::Stderr),
+        _ => Err("expected drop or stderr"),
+    }
+}
+
+/// `kind = "unbounded"`, `"bounded"` with `capacity` and `overflow`, or `"per_thread"` with `ring_size`
+fn parse_channel(t: &Table) -> Result<ChannelConfig, ConfigError> {
+    t.check_keys(&["kind", "capacity", "overflow", "overflow_level", "ring_size"])?;
+    match t.required("kind", as_str)? {
+        "unbounded" => Ok(ChannelConfig::Unbounded),
+        "bounded" => {
+            let capacity = t.required("capacity", as_usize)?;
+            if 0 == capacity {
+                return Err(t.error("capacity", "must be at least 1"));
+            } else if capacity > super::MAX_CHANNEL_CAPACITY {
+                return Err(t.error("capacity", &format!("must be at most {}", super::MAX_CHANNEL_CAPACITY)));
+            }
+            let policy = match t.optional("overflow", as_str)?.unwrap_or("block") {
+                "block" => log_channel::OverflowPolicy::Block,
+                "drop_newest" => log_channel::OverflowPolicy::DropNewest,
+                "drop_oldest_below" => log_channel::OverflowPolicy::DropOldestBelow(t.required("overflow_level", parse_level)?),
+                _ => return Err(t.error("overflow", "expected block, drop_newest or drop_oldest_below")),
+            };
+            Ok(ChannelConfig::Bounded(capacity, policy))
+        }
+        "per_thread" => Ok(ChannelConfig::PerThread(t.required("ring_size", parse_buffer_size)?)),
+        _ => Err(t.error("kind", "expected unbounded, bounded or per_thread")),
+    }
+}
+
+/// `policy = "immediate"`, `"cooldown"` with `cooldown_ms`, or `"burst"` with `count` and `window_ms`
+fn parse_trigger(t: &Table) -> Result<log_receiver::TriggerPolicy, ConfigError> {
+    t.check_keys(&["policy", "cooldown_ms", "count", "window_ms"])?;
+    match t.required("policy", as_str)? {
+        "immediate" => Ok(log_receiver::TriggerPolicy::Immediate),
+        "cooldown" => Ok(log_receiver::TriggerPolicy::Cooldown(t.required("cooldown_ms", as_duration_ms)?)),
+        "burst" => {
+            let count = t.required("count", as_usize)?;
+            if 0 == count {
+                return Err(t.error("count", "must be at least 1"));
+            }
+            Ok(log_receiver::TriggerPolicy::Burst { count, window: t.required("window_ms", as_duration_ms)? })
+        }
+        _ => Err(t.error("policy", "expected immediate, cooldown or burst")),
+    }
+}
+
+/// `kind` is one of `"file"` with `path`, `"stderr"`, `"network"` with `address` and `protocol`,
+/// `"syslog"` with `app_name`, or `"journald"` with `identifier`, followed by options common to all sinks
+fn parse_sink(t: &Table) -> Result<log_receiver::Sink, ConfigError> {
+    t.check_keys(&[
+        "kind",
+        "path",
+        "address",
+        "protocol",
+        "socket_path",
+        "syslog_format",
+        "facility",
+        "app_name",
+        "identifier",
+        "format",
+        "min_level",
+        "streaming",
+        "crash_file",
+    ])?;
+    let mut sink = match t.required("kind", as_str)? {
+        "file" => log_receiver::Sink::file(t.required("path", as_str)?).map_err(|e| t.error("path", e))?,
+        "stderr" => log_receiver::Sink::stderr(),
+        "network" => {
+            use log_receiver::circular_buffer::data_writer::NetworkProtocol;
+            let protocol = match t.optional("protocol", as_str)?.unwrap_or("tcp") {
+                "tcp" => NetworkProtocol::Tcp,
+                "udp" => NetworkProtocol::Udp,
+                _ => return Err(t.error("protocol", "expected tcp or udp")),
+            };
+            log_receiver::Sink::network(t.required("address", as_str)?, protocol)
+        }
+        #[cfg(unix)]
+        "syslog" => {
+            use log_receiver::sink::syslog;
+            let format = match t.optional("syslog_format", as_str)?.unwrap_or("rfc5424") {
+                "rfc5424" => syslog::SyslogFormat::Rfc5424,
+                "rfc3164" => syslog::SyslogFormat::Rfc3164,
+                _ => return Err(t.error("syslog_format", "expected rfc5424 or rfc3164")),
+            };
+            let facility = t.optional("facility", parse_facility)?.unwrap_or(syslog::Facility::User);
+            log_receiver::Sink::syslog(
+                t.optional("socket_path", as_str)?.unwrap_or(syslog::DEFAULT_SYSLOG_PATH),
+                format,
+                facility,
+                t.required("app_name", as_str)?,
+            )
+        }
+        #[cfg(unix)]
+        "journald" => log_receiver::Sink::journald(
+            t.optional("socket_path", as_str)?.unwrap_or(log_receiver::sink::journald::DEFAULT_JOURNALD_PATH),
+            t.required("identifier", as_str)?,
+        ),
+        _ => return Err(t.error("kind", "expected file, stderr, network, syslog or journald")),
+    };
+
+    match t.optional("format", as_str)? {
+        Some("text") | None => (),
+        Some("json") => sink = sink.with_formatter(log_receiver::sink::json_formatter()),
+        Some(_) => return Err(t.error("format", "expected text or json")),
+    }
+    if let Some(level) = t.optional("min_level", parse_level)? {
+        sink = sink.with_min_level(level);
+    }
+    if let Some(true) = t.optional("streaming", as_bool)? {
+        sink = sink.streaming();
+    }
+    if let Some(path) = t.optional("crash_file", as_str)? {
+        sink = sink.crash_safe(path);
+    }
+    Ok(sink)
+}
+
+#[cfg(unix)]
+fn parse_facility(v: &toml::Value) -> Result<log_receiver::sink::syslog::Facility, &'static str> {
+    use log_receiver::sink::syslog::Facility;
+    match as_str(v)? {
+        "kern" => Ok(Facility::Kern),
+        "user" => Ok(Facility::User),
+        "mail" => Ok(Facility::Mail),
+        "daemon" => Ok(Facility::Daemon),
+        "auth" => Ok(Facility::Auth),
+        "syslog" => Ok(Facility::Syslog),
+        "lpr" => Ok(Facility::Lpr),
+        "news" => Ok(Facility::News),
+        "uucp" => Ok(Facility::Uucp),
+        "cron" => Ok(Facility::Cron),
+        "local0" => Ok(Facility::Local0),
+        "local1" => Ok(Facility::Local1),
+        "local2" => Ok(Facility::Local2),
+        "local3" => Ok(Facility::Local3),
+        "local4" => Ok(Facility::Local4),
+        "local5" => Ok(Facility::Local5),
+        "local6" => Ok(Facility::Local6),
+        "local7" => Ok(Facility::Local7),
+        _ => Err("expected a facility name such as user, daemon or local0"),
+    }
+}
@@ -0,0 +1,220 @@
+// logger builder module
+
+use super::log_common;
+use super::log_channel;
+use super::log_receiver;
+use super::log_sender;
+
+#[cfg(feature = "config")]
+mod config;
+#[cfg(feature = "config")]
+pub use config::{ConfigError, ENV_PREFIX};
+
+
+/// Builder for a log sender and receiver pair with any number of sinks
+/// 
+/// # Example
+/// 
+/// ```rust
+/// use pragmatic_logger::{Level, BufferSize, LoggerBuilder, Sink};
+/// 
+/// fn main() -> Result::<(),String>{
+///     let log = LoggerBuilder::new(Level::Trace, Level::Error, BufferSize::Size128)
+///         .add_sink(Sink::file("/media/ramdisk/my_program_log.txt")?)
+///         .add_sink(Sink::stderr().with_min_level(Level::Warn))
+///         .build()?;
+/// 
+///     log.info_str("Running");
+///     log.shutdown();
+///     Ok(())
+/// }
+/// ```
+pub struct LoggerBuilder {
+    store_log_level: log_common::Level,
+    dump_log_level: log_common::Level,
+    buffer_size: log_receiver::BufferSize,
+    sinks: std::vec::Vec<log_receiver::Sink>,
+    channel: ChannelConfig,
+    trigger_policy: log_receiver::TriggerPolicy,
+    is_stats_in_header: bool,
+    send_failure_policy: log_sender::SendFailurePolicy,
+    run_marker: Option<std::path::PathBuf>,
+}
+
+/// Connection used from senders to the receiver
+enum ChannelConfig {
+    Unbounded,
+    Bounded(usize, log_channel::OverflowPolicy),
+    PerThread(log_receiver::BufferSize),
+}
+
+/// Largest capacity of a bounded channel, its queue is allocated up front
+const MAX_CHANNEL_CAPACITY: usize = 1 << 20;
+
+impl ChannelConfig {
+    fn validate(&self) -> Result<(), &'static str> {
+        match self {
+            Self::Bounded(0, _) => Err("Channel capacity must be at least 1"),
+            Self::Bounded(capacity, _) if *capacity > MAX_CHANNEL_CAPACITY => Err("Channel capacity must be at most 1048576"),
+            Self::PerThread(ring_size) if !ring_size.is_valid() => {
+                Err("Specified ring_size is not a supported value. Must be of type BufferSize")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl LoggerBuilder {
+
+    /// Start building a logger
+    /// 
+    /// See [`super::build_logger`] for meaning of the parameters.
+    pub fn new(
+        store_log_level: log_common::Level,
+        dump_log_level: log_common::Level,
+        buffer_size: log_receiver::BufferSize,
+    ) -> Self {
+        Self {
+            store_log_level,
+            dump_log_level,
+            buffer_size,
+            sinks: vec!(),
+            channel: ChannelConfig::Unbounded,
+            trigger_policy: log_receiver::TriggerPolicy::Immediate,
+            is_stats_in_header: false,
+            send_failure_policy: log_sender::SendFailurePolicy::Drop,
+            run_marker: None,
+        }
+    }
+
+    /// Start building a logger from the TOML configuration file at `path`
+    /// 
+    /// Top level keys are overridden by environment variables of the same name
+    /// in upper case prefixed with [`ENV_PREFIX`], such as `PRAGMATIC_LOGGER_DUMP_LEVEL=Warn`.
+    /// Settings are validated as by `build()`, errors name the key at fault.
+    /// See the README for the keys supported.
+    #[cfg(feature = "config")]
+    pub fn from_config_file(path: &str) -> Result<Self, ConfigError> {
+        config::load_file(path)
+    }
+
+    /// Same as `from_config_file()`, with the TOML already read into `contents`
+    #[cfg(feature = "config")]
+    pub fn from_config_str(contents: &str) -> Result<Self, ConfigError> {
+        config::load(contents, |name| std::env::var(name).ok())
+    }
+
+    /// Add a destination that the buffer is dumped to
+    /// 
+    /// Every sink is written on each dump.
+    pub fn add_sink(mut self, sink: log_receiver::Sink) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    /// Limit the number of messages waiting to be processed by the receiver
+    /// 
+    /// By default the channel is unbounded, so memory grows without limit
+    /// if the receiver thread stalls, for example on a slow disk during a dump.
+    /// With a bound, `policy` decides what happens when the channel is full.
+    /// The number of dropped messages is reported in the next dump.
+    /// `capacity` must be from 1 to 1048576.
+    pub fn bounded_channel(mut self, capacity: usize, policy: log_channel::OverflowPolicy) -> Self {
+        self.channel = ChannelConfig::Bounded(capacity, policy);
+        self
+    }
+
+    /// Replace the channel with a lock free ring per sending thread
+    /// 
+    /// Each thread that sends gets its own ring of `ring_size` messages,
+    /// avoiding any shared lock or channel on the hot path.
+    /// The receiver merges messages from all rings by time stamp.
+    /// Records sent while a thread's ring is full are dropped and reported in the next dump,
+    /// commands wait for the receiver to make room.
+    /// 
+    /// Messages less severe than `Warn` are picked up by polling,
+    /// so may reach the receiver a few milliseconds later than with a channel.
+    pub fn per_thread_rings(mut self, ring_size: log_receiver::BufferSize) -> Self {
+        self.channel = ChannelConfig::PerThread(ring_size);
+        self
+    }
+
+    /// Set the policy deciding when a message at or above the dump level causes a dump
+    /// 
+    /// Defaults to `TriggerPolicy::Immediate`.
+    /// Can be changed later with `LogSender::set_trigger_policy`.
+    pub fn trigger_policy(mut self, policy: log_receiver::TriggerPolicy) -> Self {
+        self.trigger_policy = policy;
+        self
+    }
+
+    /// Set what happens to messages sent after the receiver has shut down or died
+    /// 
+    /// Defaults to `SendFailurePolicy::Drop`.
+    pub fn on_send_failure(mut self, policy: log_sender::SendFailurePolicy) -> Self {
+        self.send_failure_policy = policy;
+        self
+    }
+
+    /// Keep a marker file at `path` while running, removed by a clean `LogSender::shutdown()`
+    /// 
+    /// If the marker of a previous run is still present on start,
+    /// that run ended without shutting down, such as from a crash.
+    /// An `Error` message with its pid and start time is then dumped.
+    pub fn run_marker(mut self, path: &str) -> Self {
+        self.run_marker = Some(std::path::PathBuf::from(path));
+        self
+    }
+
+    /// Write the logger's stats into the header of each dump
+    /// 
+    /// See `LogSender::stats` for the counters included.
+    pub fn stats_in_dump_header(mut self) -> Self {
+        self.is_stats_in_header = true;
+        self
+    }
+
+    /// Validate settings, spawn receiver thread and return a `LogSender`
+    pub fn build(self) -> Result<log_sender::LogSender, &'static str> {
+        if self.store_log_level < self.dump_log_level {
+            Err("Must satisfy store_log_level >= dump_log_level")
+        } else if !self.buffer_size.is_valid(){
+            Err("Specified buffer_size is not a supported value. Must be of type BufferSize")
+        } else if self.sinks.is_empty() {
+            Err("At least one sink must be added")
+        } else if let Err(e) = self.channel.validate() {
+            Err(e)
+        }
+        else {
+            self.spawn()
+        }
+    }
+
+    /// Spawn receiver thread for already validated settings
+    fn spawn(self) -> Result<log_sender::LogSender, &'static str> {
+        let (sender, receiver) = match self.channel {
+            ChannelConfig::Unbounded => log_channel::unbounded(),
+            ChannelConfig::Bounded(capacity, policy) => log_channel::bounded(capacity, policy),
+            ChannelConfig::PerThread(ring_size) => log_channel::per_thread(ring_size.value()),
+        };
+
+        let log_tx = log_sender::LogSender::new(sender, self.store_log_level)
+            .with_send_failure_policy(self.send_failure_policy);
+
+        if log_common::Level::Off != self.dump_log_level && log_common::Level::Off != self.store_log_level {
+            // Only bother to spawn receiver if data is going to be logged
+            let options = log_receiver::ReceiverOptions {
+                trigger_policy: self.trigger_policy,
+                sender_counters: log_tx.counters(),
+                is_stats_in_header: self.is_stats_in_header,
+                run_marker: self.run_marker,
+            };
+            let _log_rx_handle = log_receiver::spawn(self.dump_log_level, receiver, self.sinks, self.buffer_size, options)?;
+        } else {
+            // Nothing will be logged, so messages are filtered rather than failing to send
+            log_tx.set_store_level(log_common::Level::Off);
+        }
+
+        Ok(log_tx)
+    }
+}